@@ -1,9 +1,59 @@
+use std::error::Error;
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::net::{UdpSocket, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::io::{self, Read, Write};
+use std::net::{UdpSocket, IpAddr, SocketAddr};
+use std::os::fd::{AsRawFd, RawFd};
 use std::str::FromStr;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant, SystemTime};
 use crate::log::{log, LogEntry, LogLevel};
-#[derive(Clone)]
+
+pub mod tcp;
+pub mod unix_socket;
+pub mod fifo;
+pub mod shared_memory;
+pub mod message_queue;
+pub mod signal;
+pub mod serial;
+pub mod can;
+pub mod canopen;
+pub mod manager;
+pub mod reconnect;
+pub mod framing;
+pub mod checksum;
+pub mod stats;
+pub mod events;
+pub mod async_interface;
+pub mod config;
+pub mod recording;
+pub mod pcap;
+pub mod replay;
+pub mod loopback;
+pub mod mock;
+pub mod fault;
+mod reactor;
+mod socket;
+
+pub use manager::{InterfaceHandle, InterfaceManager};
+pub use reconnect::{GiveUpAction, ReconnectPolicy};
+pub use framing::{Endianness, FramedInterface, Framing, PrefixWidth};
+pub use checksum::{Checksum, ChecksumInterface};
+pub use stats::InterfaceStats;
+pub use events::{InterfaceEventRecord, SubscriptionId};
+pub use config::{ConfigError, ConfigIssue, InterfaceConfig, InterfacesConfig};
+pub use recording::{RecordedTraffic, Recorder, RecordingInterface, RecordingReader, TrafficDirection};
+pub use pcap::{CapturedPacket, PcapFilter, PcapInterface, TransportProtocol};
+pub use replay::{FrameRecorder, ReplayControl, ReplayInterface};
+pub use loopback::LoopbackInterface;
+pub use mock::{MockCall, MockInterface};
+pub use fault::{FaultInterface, FaultKind, FaultPolicy};
+use events::EventSubscribers;
+pub use async_interface::{AsyncFileInterface, AsyncInterfaceTrait, AsyncUdpInterface, BlockingAdapter,
+                          InterfaceFuture, WorkerReply};
+use reconnect::ReconnectState;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PhysInterface {
     None,
     Serial,
@@ -14,7 +64,7 @@ pub enum PhysInterface {
     RS232,
     RS485,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum LogicalInterface {
     File,
     Socket,
@@ -23,26 +73,39 @@ pub enum LogicalInterface {
     MessageQueue,
     Signal,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InterfaceType {
     phys: PhysInterface,
     logic: LogicalInterface,
 }
 
-#[derive(Clone)]
+impl InterfaceType {
+    pub fn new(phys: PhysInterface, logic: LogicalInterface) -> Self {
+        InterfaceType { phys, logic }
+    }
+    pub fn get_phys(&self) -> PhysInterface {
+        self.phys.clone()
+    }
+    pub fn get_logic(&self) -> LogicalInterface {
+        self.logic.clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InterfaceStatus {
     Connected,
     Disconnected,
+    Reconnecting,
     Error,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InterfaceMode {
     Read,
     Write,
     ReadWrite,
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InterfaceProtocol {
     Raw,
     TcpIp,
@@ -50,8 +113,8 @@ pub enum InterfaceProtocol {
     CANopen,
     EtherCAT,
 }
-#[derive(Clone)]
-pub enum InterfaceError {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InterfaceErrorKind {
     Timeout,
     Overflow,
     Underflow,
@@ -64,37 +127,112 @@ pub enum InterfaceError {
     NotOpenIFace,
     AlreadyOpenIFace,
     NotValidSocketAddr,
+    ConnectionLost,
+    UnknownInterface,
+    DuplicateInterface,
+    IoError,
     GenericError,
 }
-impl ToString for InterfaceError {
-    fn to_string(&self) -> String {
-        match self {
-            InterfaceError::Timeout => "Timeout".to_string(),
-            InterfaceError::Overflow => "Overflow".to_string(),
-            InterfaceError::Underflow => "Underflow".to_string(),
-            InterfaceError::FramingError => "Framing Error".to_string(),
-            InterfaceError::ParityError => "Parity Error".to_string(),
-            InterfaceError::ChecksumError => "Checksum Error".to_string(),
-            InterfaceError::ProtocolError => "Protocol Error".to_string(),
-            InterfaceError::WriteOnReadOnly => "Write on Read Only".to_string(),
-            InterfaceError::ReadOnWriteOnly => "Read on Write Only".to_string(),
-            InterfaceError::NotOpenIFace => "Interface not open".to_string(),
-            InterfaceError::AlreadyOpenIFace => "Interface already open".to_string(),
-            InterfaceError::NotValidSocketAddr => "Not valid socket address".to_string(),
-            InterfaceError::GenericError => "Unpredictable error".to_string(),
+impl InterfaceErrorKind {
+    pub fn from_io(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => InterfaceErrorKind::Timeout,
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => InterfaceErrorKind::ConnectionLost,
+            io::ErrorKind::InvalidData => InterfaceErrorKind::ProtocolError,
+            io::ErrorKind::AddrNotAvailable | io::ErrorKind::AddrInUse => InterfaceErrorKind::NotValidSocketAddr,
+            _ => InterfaceErrorKind::IoError,
+        }
+    }
+}
+impl fmt::Display for InterfaceErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            InterfaceErrorKind::Timeout => "Timeout",
+            InterfaceErrorKind::Overflow => "Overflow",
+            InterfaceErrorKind::Underflow => "Underflow",
+            InterfaceErrorKind::FramingError => "Framing Error",
+            InterfaceErrorKind::ParityError => "Parity Error",
+            InterfaceErrorKind::ChecksumError => "Checksum Error",
+            InterfaceErrorKind::ProtocolError => "Protocol Error",
+            InterfaceErrorKind::WriteOnReadOnly => "Write on Read Only",
+            InterfaceErrorKind::ReadOnWriteOnly => "Read on Write Only",
+            InterfaceErrorKind::NotOpenIFace => "Interface not open",
+            InterfaceErrorKind::AlreadyOpenIFace => "Interface already open",
+            InterfaceErrorKind::NotValidSocketAddr => "Not valid socket address",
+            InterfaceErrorKind::ConnectionLost => "Connection lost",
+            InterfaceErrorKind::UnknownInterface => "Unknown interface",
+            InterfaceErrorKind::DuplicateInterface => "Duplicate interface",
+            InterfaceErrorKind::IoError => "I/O error",
+            InterfaceErrorKind::GenericError => "Unpredictable error",
+        };
+        f.write_str(text)
+    }
+}
+
+// Returned by every interface operation: what went wrong, on which interface, and the
+// lower level error that caused it when there is one
+#[derive(Debug)]
+pub struct InterfaceError {
+    kind: InterfaceErrorKind,
+    interface: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+impl InterfaceError {
+    pub fn new(kind: InterfaceErrorKind, interface: &str) -> Self {
+        InterfaceError {
+            kind,
+            interface: interface.to_string(),
+            source: None,
+        }
+    }
+    pub fn with_source(kind: InterfaceErrorKind, interface: &str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        InterfaceError {
+            kind,
+            interface: interface.to_string(),
+            source: Some(source.into()),
         }
     }
+    pub fn kind(&self) -> InterfaceErrorKind {
+        self.kind
+    }
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+    // The io::Error behind this error, if it was caused by one
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.as_ref().and_then(|source| source.downcast_ref::<io::Error>())
+    }
 }
-#[derive(Clone)]
+impl fmt::Display for InterfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interface {}: {}", self.interface, self.kind)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+impl Error for InterfaceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum InterfaceEvent {
     DataReceived,
     DataSent,
     ConnectionEstablished,
     ConnectionLost,
     ErrorOccurred,
+    SignalReceived(i32),
 }
 
-struct BaseInterface {
+pub struct BaseInterface {
     name: String,
     description: String,
     status: InterfaceStatus,
@@ -102,8 +240,38 @@ struct BaseInterface {
     interface_type: InterfaceType,
     interface_protocol: InterfaceProtocol,
     log_interface: bool,
-    error: Option<InterfaceError>,
+    error: Option<InterfaceErrorKind>,
     event: Option<InterfaceEvent>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    nonblocking: bool,
+    reconnect: Option<ReconnectState>,
+    stats: InterfaceStats,
+    subscribers: EventSubscribers,
+}
+
+// poll(2) on a single descriptor, restarted on EINTR; Ok(false) once `timeout` ran out
+fn poll_ready(fd: RawFd, events: libc::c_short, timeout: Option<Duration>) -> io::Result<bool> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let timeout_ms = match deadline {
+            // Rounded up, a sub-millisecond remainder must not turn into a busy loop
+            Some(deadline) => deadline.saturating_duration_since(Instant::now())
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+        let mut poll_fd = libc::pollfd { fd, events, revents: 0 };
+        let ret = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+        if ret >= 0 {
+            return Ok(ret > 0);
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
 }
 
 impl BaseInterface {
@@ -131,69 +299,267 @@ impl BaseInterface {
             },
             error: None,
             event: None,
+            read_timeout: None,
+            write_timeout: None,
+            nonblocking: false,
+            reconnect: None,
+            stats: InterfaceStats::new(),
+            subscribers: EventSubscribers::new(),
         }
     }
-    fn get_name(&self) -> String {
+    pub fn get_name(&self) -> String {
         self.name.clone()
     }
-    fn get_description(&self) -> String {
+    pub fn get_description(&self) -> String {
         self.description.clone()
     }
-    fn get_type(&self) -> InterfaceType {
+    pub fn get_type(&self) -> InterfaceType {
         self.interface_type.clone()
     }
-    fn get_status(&self) -> InterfaceStatus {
+    pub fn get_status(&self) -> InterfaceStatus {
         self.status.clone()
     }
-    fn get_mode(&self) -> InterfaceMode {
+    pub fn get_mode(&self) -> InterfaceMode {
         self.mode.clone()
     }
-    fn get_protocol(&self) -> InterfaceProtocol {
+    pub fn get_protocol(&self) -> InterfaceProtocol {
         self.interface_protocol.clone()
     }
-    fn get_error(&self) -> Option<InterfaceError> {
-        self.error.clone()
+    pub fn get_error(&self) -> Option<InterfaceErrorKind> {
+        self.error
     }
-    fn get_event(&self) -> Option<InterfaceEvent> {
+    pub fn get_event(&self) -> Option<InterfaceEvent> {
         self.event.clone()
     }
-    fn is_log_interface(&self) -> bool {
+    pub fn is_log_interface(&self) -> bool {
         self.log_interface.clone()
     }
+    pub fn get_read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+    pub fn get_write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+    // Connections re-established by the reconnect policy since the interface was created
+    pub fn get_reconnect_count(&self) -> u64 {
+        self.stats.get_reconnects()
+    }
+    pub fn get_stats(&self) -> &InterfaceStats {
+        &self.stats
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+    // Callbacks run on the thread that triggered the event, in the middle of the read,
+    // write, open or close call, so they should return quickly
+    pub fn subscribe<F: FnMut(&InterfaceEventRecord) + Send + 'static>(&mut self, callback: F) -> SubscriptionId {
+        self.subscribers.add_callback(Box::new(callback))
+    }
+    // Events queue up in the channel until received, dropping the receiver unsubscribes
+    pub fn subscribe_channel(&mut self) -> (SubscriptionId, Receiver<InterfaceEventRecord>) {
+        self.subscribers.add_channel()
+    }
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.subscribers.remove(id)
+    }
+    // Span the throughput figures of get_stats are averaged over, 10 seconds by default
+    pub fn set_throughput_window(&mut self, window: Duration) {
+        self.stats.set_window(window);
+    }
+
+    // How long a read or write may wait: None blocks, non-blocking mode does not wait at all
+    fn wait_timeout(&self, write: bool) -> Option<Duration> {
+        if self.nonblocking {
+            return Some(Duration::ZERO);
+        }
+        if write { self.write_timeout } else { self.read_timeout }
+    }
+
+    // Waits until `fd` is readable (or writable) within the interface timeout
+    fn wait_ready(&mut self, fd: RawFd, write: bool) -> Result<(), InterfaceError> {
+        let timeout = match self.wait_timeout(write) {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        let events = if write { libc::POLLOUT } else { libc::POLLIN };
+        match poll_ready(fd, events, Some(timeout)) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(self.timed_out()),
+            Err(e) => Err(self.raise_io(e)),
+        }
+    }
+
+    // Timeouts are expected when polling, so unlike the other errors they are not logged
+    fn timed_out(&mut self) -> InterfaceError {
+        self.raise_quiet(InterfaceErrorKind::Timeout)
+    }
+
+    fn raise_quiet(&mut self, kind: InterfaceErrorKind) -> InterfaceError {
+        self.error = Some(kind);
+        self.stats.record_error(kind);
+        InterfaceError::new(kind, &self.name)
+    }
+
+    fn set_event(&mut self, event: InterfaceEvent) {
+        self.emit(event, None);
+    }
+
+    fn emit(&mut self, event: InterfaceEvent, error: Option<InterfaceErrorKind>) {
+        if let InterfaceEvent::ConnectionLost = event {
+            self.stats.record_connection_lost();
+        }
+        if !self.subscribers.is_empty() {
+            self.subscribers.notify(&InterfaceEventRecord {
+                interface: self.name.clone(),
+                event: event.clone(),
+                error,
+                timestamp: SystemTime::now(),
+            });
+        }
+        self.event = Some(event);
+    }
+
+    fn data_received(&mut self, bytes: usize) {
+        self.stats.record_read(bytes);
+        self.set_event(InterfaceEvent::DataReceived);
+    }
+
+    fn data_sent(&mut self, bytes: usize) {
+        self.stats.record_write(bytes);
+        self.set_event(InterfaceEvent::DataSent);
+    }
 
-    fn set_error(&mut self, error: InterfaceError) {
+    fn set_error(&mut self, error: InterfaceErrorKind) {
         self.error = Some(error);
-        self.log_error();
+        self.stats.record_error(error);
+        self.log_error(None);
+        self.emit(InterfaceEvent::ErrorOccurred, Some(error));
+    }
+
+    // Records the error kind like set_error and builds the error handed to the caller
+    fn raise(&mut self, kind: InterfaceErrorKind) -> InterfaceError {
+        self.set_error(kind);
+        InterfaceError::new(kind, &self.name)
+    }
+
+    fn raise_with(&mut self, kind: InterfaceErrorKind, source: impl Into<Box<dyn Error + Send + Sync>>) -> InterfaceError {
+        let error = InterfaceError::with_source(kind, &self.name, source);
+        self.error = Some(kind);
+        self.stats.record_error(kind);
+        self.log_error(error.source.as_deref());
+        self.emit(InterfaceEvent::ErrorOccurred, Some(kind));
+        error
+    }
+
+    fn raise_io(&mut self, source: io::Error) -> InterfaceError {
+        self.raise_with(InterfaceErrorKind::from_io(source.kind()), source)
     }
 
-    fn log_error(&mut self) {
+    fn log_error(&mut self, source: Option<&(dyn Error + Send + Sync)>) {
         if self.is_log_interface() {
             return;
         }
         if let Some(error) = self.get_error() {
+            let message = match source {
+                Some(source) => format!("{}: {}", error, source),
+                None => error.to_string(),
+            };
             log().write(LogEntry::new(
                 LogLevel::ERR,
                 format!("interface:{}", self.get_name()),
-                format!("{}", error.to_string()),
+                message,
             ));
         }
     }
 }
 
 pub trait InterfaceTrait {
-    fn open(&mut self) -> Result<(), String>;
-    fn close(&mut self) -> Result<(), String>;
-    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, String>;
-    fn write(&mut self, buffer: &[u8]) -> Result<(), String>;
+    fn base_interface(&self) -> &BaseInterface;
+    fn base_interface_mut(&mut self) -> &mut BaseInterface;
+    fn open(&mut self) -> Result<(), InterfaceError>;
+    fn close(&mut self) -> Result<(), InterfaceError>;
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError>;
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError>;
+
+    // Descriptors that turn readable when input arrives, empty while closed or when the
+    // interface has none to offer (the manager then checks has_pending_input periodically)
+    fn poll_fds(&self) -> Vec<RawFd> {
+        Vec::new()
+    }
+    // Input already buffered by the interface, which polling its descriptors would miss
+    fn has_pending_input(&self) -> bool {
+        false
+    }
+    // None, the default, blocks until the read or write can go ahead; an expired
+    // timeout fails with InterfaceErrorKind::Timeout
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.base_interface_mut().read_timeout = timeout;
+    }
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.base_interface_mut().write_timeout = timeout;
+    }
+    // Reads and writes that can not go ahead straight away fail with Timeout
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.base_interface_mut().nonblocking = nonblocking;
+    }
+    // Only used by interfaces that can tell when their connection dropped; None leaves a
+    // dropped interface Disconnected until it is opened again
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.base_interface_mut().reconnect = policy.map(ReconnectState::new);
+    }
+}
+
+// Lets wrappers such as FramedInterface sit on top of interfaces held as trait objects
+impl<I: InterfaceTrait + ?Sized> InterfaceTrait for Box<I> {
+    fn base_interface(&self) -> &BaseInterface {
+        (**self).base_interface()
+    }
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        (**self).base_interface_mut()
+    }
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        (**self).open()
+    }
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        (**self).close()
+    }
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        (**self).read(buffer)
+    }
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        (**self).write(buffer)
+    }
+    fn poll_fds(&self) -> Vec<RawFd> {
+        (**self).poll_fds()
+    }
+    fn has_pending_input(&self) -> bool {
+        (**self).has_pending_input()
+    }
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        (**self).set_read_timeout(timeout)
+    }
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        (**self).set_write_timeout(timeout)
+    }
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        (**self).set_nonblocking(nonblocking)
+    }
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        (**self).set_reconnect_policy(policy)
+    }
 }
 
 pub trait IsInterfaceManager {
-    fn add_interface(&mut self, interface: Box<dyn InterfaceTrait>) -> Result<(), String>;
-    fn remove_interface(&mut self, interface: &Box<dyn InterfaceTrait>) -> Result<(), String>;
-    fn get_interface(&self, index: u32) -> Option<&Box<dyn InterfaceTrait>>;
+    fn add_interface(&mut self, interface: Box<dyn InterfaceTrait>) -> Result<InterfaceHandle, InterfaceError>;
+    fn remove_interface(&mut self, handle: InterfaceHandle) -> Result<Box<dyn InterfaceTrait>, InterfaceError>;
+    fn get_interface(&self, index: u32) -> Option<&dyn InterfaceTrait>;
+    fn get_interface_mut(&mut self, index: u32) -> Option<&mut dyn InterfaceTrait>;
     fn get_interface_count(&self) -> u32;
-    fn open_all_interfaces(&mut self) -> Result<(), String>;
-    fn close_all_interfaces(&mut self) -> Result<(), String>;
+    fn open_all_interfaces(&mut self) -> Result<(), Vec<InterfaceError>>;
+    fn close_all_interfaces(&mut self) -> Result<(), Vec<InterfaceError>>;
 }
 pub struct FileInterface {
     file_path: String,
@@ -216,57 +582,62 @@ impl FileInterface {
     }
 }
 impl InterfaceTrait for FileInterface {
-    fn open(&mut self) -> Result<(), String> {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.file.iter().map(|file| file.as_raw_fd()).collect()
+    }
+    fn open(&mut self) -> Result<(), InterfaceError> {
         match  self.base_interface.get_status() {
             InterfaceStatus::Connected => {
-                self.base_interface.set_error(InterfaceError::AlreadyOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
             }
             _ => {}
         }
         match self.base_interface.get_mode() {
             InterfaceMode::Read => {
-                self.file = Some(File::open(&self.file_path).map_err(|e| e.to_string())?);
+                self.file = Some(File::open(&self.file_path).map_err(|e| self.base_interface.raise_io(e))?);
             }
             InterfaceMode::Write => {
-                self.file = Some(File::create(&self.file_path).map_err(|e| e.to_string())?);
+                self.file = Some(File::create(&self.file_path).map_err(|e| self.base_interface.raise_io(e))?);
             }
             InterfaceMode::ReadWrite => {
                 self.file = Some(OpenOptions::new()
                     .read(true)
                     .write(true)
                     .open(&self.file_path)
-                    .map_err(|e| e.to_string())?);
+                    .map_err(|e| self.base_interface.raise_io(e))?);
             }
         }
         self.base_interface.status = InterfaceStatus::Connected;
         Ok(())
     }
-    fn close(&mut self) -> Result<(), String> {
+    fn close(&mut self) -> Result<(), InterfaceError> {
         match self.base_interface.status {
             InterfaceStatus::Disconnected => {
-                self.base_interface.set_error(InterfaceError::NotOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
             }
             _ => {}
         }
         if self.file.is_some() {
             if let Some(file) = self.file.take() {
-                file.sync_all().map_err(|e| e.to_string())?;
+                file.sync_all().map_err(|e| self.base_interface.raise_io(e))?;
             }
             self.base_interface.status = InterfaceStatus::Disconnected;
             Ok(())
         } else {
-            self.base_interface.set_error(InterfaceError::NotOpenIFace);
-            return Err(self.base_interface.error.clone().unwrap().to_string());
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
         }
     }
 
-    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, String> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
         match self.base_interface.mode {
             InterfaceMode::Write => {
-                self.base_interface.set_error(InterfaceError::WriteOnReadOnly);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
             }
             _ => {}
         }
@@ -274,25 +645,27 @@ impl InterfaceTrait for FileInterface {
             InterfaceStatus::Connected => {
                 self.base_interface.error = None;
                 if let Some(file) = self.file.as_mut() {
-                    let bytes_read = file.read(buffer).map_err(|e| e.to_string())?;
+                    self.base_interface.wait_ready(file.as_raw_fd(), false)?;
+                    let bytes_read = file.read(buffer).map_err(|e| self.base_interface.raise_io(e))?;
+                    // End of file is not a message
+                    if bytes_read > 0 {
+                        self.base_interface.data_received(bytes_read);
+                    }
                     return Ok(bytes_read as u32);
                 } else {
-                    self.base_interface.set_error(InterfaceError::GenericError);
-                    return Err(self.base_interface.error.clone().unwrap().to_string());
+                    return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
                 }
             }
             _ => {
-                self.base_interface.set_error(InterfaceError::NotOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
             }
         }
     }
 
-    fn write(&mut self, buffer: &[u8]) -> Result<(), String> {
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
         match self.base_interface.mode {
             InterfaceMode::Read => {
-                self.base_interface.set_error(InterfaceError::ReadOnWriteOnly);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
             }
             _ => {}
         }
@@ -300,17 +673,17 @@ impl InterfaceTrait for FileInterface {
             InterfaceStatus::Connected => {
                 self.base_interface.error = None;
                 if let Some(file) = self.file.as_mut() {
-                    file.write_all(buffer).map_err(|e| e.to_string())?;
+                    self.base_interface.wait_ready(file.as_raw_fd(), true)?;
+                    file.write_all(buffer).map_err(|e| self.base_interface.raise_io(e))?;
+                    self.base_interface.data_sent(buffer.len());
                     return Ok(());
                 }
                 else {
-                    self.base_interface.set_error(InterfaceError::GenericError);
-                    return Err(self.base_interface.error.clone().unwrap().to_string());
+                    return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
                 }
             }
             _ => {
-                self.base_interface.set_error(InterfaceError::NotOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
             }
         }
     }
@@ -323,7 +696,23 @@ pub struct UDPInterface {
     remote_addr: String,
     remote_port: u16,
     remote_socket_addr: Option<std::net::SocketAddr>,
-    multicast: bool,
+    broadcast: bool,
+    ttl: Option<u32>,
+    multicast_ttl: Option<u32>,
+    multicast_loop: bool,
+    multicast_if: Option<String>,
+    multicast_if_index: u32,
+    multicast_groups: Vec<IpAddr>,
+    multicast_sources: Vec<IpAddr>,
+    // Memberships currently held by the socket, left again on close
+    joined: Vec<(IpAddr, Option<IpAddr>)>,
+    reuse_address: bool,
+    reuse_port: bool,
+    receive_buffer_size: Option<usize>,
+    source_filter: Vec<IpAddr>,
+    filtered_count: u64,
+    reply_to_sender: bool,
+    last_sender: Option<SocketAddr>,
     base_interface: BaseInterface,
 }
 impl UDPInterface {
@@ -338,7 +727,22 @@ impl UDPInterface {
             remote_port: 0,
             socket: None,
             remote_socket_addr: None,
-            multicast: false,
+            broadcast: false,
+            ttl: None,
+            multicast_ttl: None,
+            multicast_loop: true,
+            multicast_if: None,
+            multicast_if_index: 0,
+            multicast_groups: Vec::new(),
+            multicast_sources: Vec::new(),
+            joined: Vec::new(),
+            reuse_address: false,
+            reuse_port: false,
+            receive_buffer_size: None,
+            source_filter: Vec::new(),
+            filtered_count: 0,
+            reply_to_sender: false,
+            last_sender: None,
             base_interface: BaseInterface::new(name,
                                             description,
                                             InterfaceType{phys: PhysInterface::Ethernet, logic: LogicalInterface::Socket},
@@ -350,139 +754,340 @@ impl UDPInterface {
     pub fn append_remote_addr(&mut self, remote_ip: String, remote_port: u16) {
         match self.base_interface.get_mode() {
             InterfaceMode::Read => {
-                self.base_interface.set_error(InterfaceError::ReadOnWriteOnly);
+                self.base_interface.set_error(InterfaceErrorKind::ReadOnWriteOnly);
                 return;
             }
             _ => {}
         }
         let socket_addr = format!("{}:{}", remote_ip, remote_port);
         if socket_addr.parse::<std::net::SocketAddr>().is_err() {
-            self.base_interface.set_error(InterfaceError::NotValidSocketAddr);
+            self.base_interface.set_error(InterfaceErrorKind::NotValidSocketAddr);
             return;
         }
 
         self.remote_addr = remote_ip;
         self.remote_port = remote_port;
     }
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.as_ref().and_then(|socket| socket.local_addr().ok())
+    }
+    pub fn set_broadcast(&mut self, broadcast: bool) -> Result<(), InterfaceError> {
+        self.broadcast = broadcast;
+        self.apply_socket_options()
+    }
+    // Unicast TTL (hop limit on IPv6), None keeps the system default
+    pub fn set_ttl(&mut self, ttl: Option<u32>) -> Result<(), InterfaceError> {
+        self.ttl = ttl;
+        self.apply_socket_options()
+    }
+    pub fn set_multicast_ttl(&mut self, ttl: Option<u32>) -> Result<(), InterfaceError> {
+        self.multicast_ttl = ttl;
+        self.apply_socket_options()
+    }
+    pub fn set_multicast_loop(&mut self, multicast_loop: bool) -> Result<(), InterfaceError> {
+        self.multicast_loop = multicast_loop;
+        self.apply_socket_options()
+    }
+    // Network interface ("eth0") groups are joined on and multicast is sent from,
+    // None lets the kernel pick by route. Takes effect on the next open.
+    pub fn set_multicast_interface(&mut self, if_name: Option<String>) {
+        self.multicast_if = if_name;
+    }
+    // Joined on open in addition to a multicast remote address, or right away when already open
+    pub fn join_multicast_group(&mut self, group: IpAddr) -> Result<(), InterfaceError> {
+        if !group.is_multicast() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotValidSocketAddr));
+        }
+        if !self.multicast_groups.contains(&group) {
+            self.multicast_groups.push(group);
+        }
+        if self.socket.is_some() {
+            self.join_group(group)?;
+        }
+        Ok(())
+    }
+    pub fn leave_multicast_group(&mut self, group: IpAddr) -> Result<(), InterfaceError> {
+        self.multicast_groups.retain(|joined| *joined != group);
+        self.leave_groups(|joined| joined == group)
+    }
+    // With sources every group is joined source-specific, only their datagrams are delivered.
+    // Takes effect on the next open.
+    pub fn set_multicast_sources(&mut self, sources: Vec<IpAddr>) {
+        self.multicast_sources = sources;
+    }
+    // Lets several receivers on one host bind the same port, takes effect on the next open
+    pub fn set_reuse_address(&mut self, reuse_address: bool) {
+        self.reuse_address = reuse_address;
+    }
+    pub fn set_reuse_port(&mut self, reuse_port: bool) {
+        self.reuse_port = reuse_port;
+    }
+    pub fn set_receive_buffer_size(&mut self, size: Option<usize>) -> Result<(), InterfaceError> {
+        self.receive_buffer_size = size;
+        self.apply_socket_options()
+    }
+    // The size the kernel actually granted while open, the requested one otherwise
+    pub fn get_receive_buffer_size(&self) -> Option<usize> {
+        match self.socket.as_ref() {
+            Some(socket) => socket::receive_buffer_size(socket).ok(),
+            None => self.receive_buffer_size,
+        }
+    }
+    // Datagrams from any other address are dropped on read, an empty filter accepts all
+    pub fn set_source_filter(&mut self, sources: Vec<IpAddr>) {
+        self.source_filter = sources.into_iter().map(|source| source.to_canonical()).collect();
+    }
+    // Datagrams dropped by the source filter since the interface was created
+    pub fn get_filtered_count(&self) -> u64 {
+        self.filtered_count
+    }
+    // Writes go to whoever sent the last datagram, the remote address until something arrived
+    pub fn set_reply_to_sender(&mut self, reply_to_sender: bool) {
+        self.reply_to_sender = reply_to_sender;
+    }
+    pub fn get_last_sender(&self) -> Option<SocketAddr> {
+        self.last_sender
+    }
+    // Like read, but also returns who sent the datagram and when the kernel received it
+    pub fn read_from(&mut self, buffer: &mut [u8]) -> Result<(u32, SocketAddr, SystemTime), InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        let (bytes_read, source, timestamp) = self.receive(buffer)?;
+        self.base_interface.data_received(bytes_read);
+        Ok((bytes_read as u32, source, timestamp))
+    }
+
+    fn local_socket_addr(&mut self) -> Result<SocketAddr, InterfaceError> {
+        format!("{}:{}", self.ip_address, self.port)
+            .parse::<SocketAddr>()
+            .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::NotValidSocketAddr, e))
+    }
+
+    fn apply_socket_options(&mut self) -> Result<(), InterfaceError> {
+        let socket = match self.socket.as_ref() {
+            Some(socket) => socket,
+            None => return Ok(()),
+        };
+        let ipv4 = socket.local_addr().map(|addr| addr.is_ipv4()).unwrap_or(true);
+        let result = (|| {
+            socket::enable_timestamps(socket)?;
+            socket.set_broadcast(self.broadcast)?;
+            if ipv4 {
+                socket.set_multicast_loop_v4(self.multicast_loop)?;
+                if let Some(ttl) = self.ttl {
+                    socket.set_ttl(ttl)?;
+                }
+                if let Some(ttl) = self.multicast_ttl {
+                    socket.set_multicast_ttl_v4(ttl)?;
+                }
+            } else {
+                socket.set_multicast_loop_v6(self.multicast_loop)?;
+                if let Some(hops) = self.ttl {
+                    socket::set_hop_limit_v6(socket, false, hops)?;
+                }
+                if let Some(hops) = self.multicast_ttl {
+                    socket::set_hop_limit_v6(socket, true, hops)?;
+                }
+            }
+            if self.multicast_if_index != 0 {
+                socket::set_multicast_interface(socket, ipv4, self.multicast_if_index)?;
+            }
+            if let Some(size) = self.receive_buffer_size {
+                socket::set_receive_buffer_size(socket, size)?;
+            }
+            Ok(())
+        })();
+        result.map_err(|e| self.base_interface.raise_io(e))
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        match self.last_sender {
+            Some(sender) if self.reply_to_sender => Some(sender),
+            _ => self.remote_socket_addr,
+        }
+    }
+
+    fn join_group(&mut self, group: IpAddr) -> Result<(), InterfaceError> {
+        let socket = match self.socket.as_ref() {
+            Some(socket) => socket,
+            None => return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+        };
+        let sources: Vec<Option<IpAddr>> = if self.multicast_sources.is_empty() {
+            vec![None]
+        } else {
+            self.multicast_sources.iter()
+                .filter(|source| source.is_ipv4() == group.is_ipv4())
+                .map(|source| Some(*source))
+                .collect()
+        };
+        for source in sources {
+            if self.joined.contains(&(group, source)) {
+                continue;
+            }
+            socket::multicast_membership(socket, true, group, source, self.multicast_if_index)
+                .map_err(|e| self.base_interface.raise_io(e))?;
+            self.joined.push((group, source));
+        }
+        Ok(())
+    }
+
+    // A multicast remote address is joined as well, so the interface hears the group it sends to
+    fn join_configured_groups(&mut self) -> Result<(), InterfaceError> {
+        let mut groups = self.multicast_groups.clone();
+        if let Ok(remote_ip) = IpAddr::from_str(&self.remote_addr) {
+            if remote_ip.is_multicast() && !groups.contains(&remote_ip) {
+                groups.insert(0, remote_ip);
+            }
+            self.remote_socket_addr = Some(SocketAddr::new(remote_ip, self.remote_port));
+        }
+        self.apply_socket_options()?;
+        for group in groups {
+            self.join_group(group)?;
+        }
+        Ok(())
+    }
+
+    // Every matching membership is left even when one of them fails, the first error is returned
+    fn leave_groups<F: Fn(IpAddr) -> bool>(&mut self, matches: F) -> Result<(), InterfaceError> {
+        let mut result = Ok(());
+        let mut kept = Vec::new();
+        for (group, source) in std::mem::take(&mut self.joined) {
+            if !matches(group) {
+                kept.push((group, source));
+                continue;
+            }
+            if let Some(socket) = self.socket.as_ref()
+                && let Err(e) = socket::multicast_membership(socket, false, group, source, self.multicast_if_index)
+                && result.is_ok() {
+                result = Err(self.base_interface.raise_io(e));
+            }
+        }
+        self.joined = kept;
+        result
+    }
+
+    // Waits within the read timeout for a datagram that passes the source filter
+    fn receive(&mut self, buffer: &mut [u8]) -> Result<(usize, SocketAddr, SystemTime), InterfaceError> {
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        loop {
+            let socket = match self.socket.as_ref() {
+                Some(socket) => socket,
+                None => return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            if let Some(deadline) = deadline {
+                match poll_ready(socket.as_raw_fd(), libc::POLLIN, Some(deadline.saturating_duration_since(Instant::now()))) {
+                    Ok(true) => {}
+                    Ok(false) => return Err(self.base_interface.timed_out()),
+                    Err(e) => return Err(self.base_interface.raise_io(e)),
+                }
+            }
+            let (bytes_read, source, timestamp) = socket::recv_timestamped(socket, buffer)
+                .map_err(|e| self.base_interface.raise_io(e))?;
+            if self.accepts(&source) {
+                // Only missing when timestamps could not be enabled, arrival is close enough then
+                return Ok((bytes_read, source, timestamp.unwrap_or_else(SystemTime::now)));
+            }
+        }
+    }
+
+    fn accepts(&mut self, source: &SocketAddr) -> bool {
+        if self.source_filter.is_empty() || self.source_filter.contains(&source.ip().to_canonical()) {
+            self.last_sender = Some(*source);
+            return true;
+        }
+        self.filtered_count += 1;
+        false
+    }
 }
 
 impl InterfaceTrait for UDPInterface {
-    fn open(&mut self) -> Result<(), String> {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.socket.iter().map(|socket| socket.as_raw_fd()).collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
         match  self.base_interface.get_status() {
             InterfaceStatus::Connected => {
-                self.base_interface.set_error(InterfaceError::AlreadyOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
             }
             _ => {}
         }
-        // Implement UDP connection opening logic here
-        self.socket = Some(UdpSocket::bind((self.ip_address.as_str(), self.port)).map_err(|e| e.to_string())?);
-        let remote_ip_addr = self.remote_addr.as_str();
-        match IpAddr::from_str(remote_ip_addr) {
-            Ok(ip_addr) => {
-                if ip_addr.is_multicast() {
-                    let socket = self.socket.as_ref().unwrap();
-                    if ip_addr.is_ipv4() {
-                        let ipv4 = Ipv4Addr::from_str(remote_ip_addr).map_err(|e| e.to_string())?;
-                        socket.set_multicast_loop_v4(true).map_err(|e| e.to_string())?;
-                        socket.join_multicast_v4(&ipv4, &Ipv4Addr::new(0, 0, 0, 0))
-                                .map_err(|e| e.to_string())?;
-                    }
-                    else {
-                        let ipv6 = Ipv6Addr::from_str(remote_ip_addr).map_err(|e| e.to_string())?;
-                        socket.set_multicast_loop_v6(true).map_err(|e| e.to_string())?;
-                        socket.join_multicast_v6(&ipv6, 0).map_err(|e| e.to_string())?;
-                    }
-                }
-                self.remote_socket_addr = Some(format!("{}:{}", remote_ip_addr, self.remote_port)
-                                            .parse::<std::net::SocketAddr>().map_err(|e| e.to_string())?);
-            }
-            Err(_) => {}
+        let local_addr = self.local_socket_addr()?;
+        self.multicast_if_index = match self.multicast_if.as_deref() {
+            Some(if_name) => socket::interface_index(if_name).map_err(|e| self.base_interface.raise_io(e))?,
+            None => 0,
+        };
+        self.socket = Some(socket::bind_udp(&local_addr, self.reuse_address, self.reuse_port, self.receive_buffer_size)
+            .map_err(|e| self.base_interface.raise_io(e))?);
+        if let Err(e) = self.join_configured_groups() {
+            // Dropping the socket also drops whatever memberships were joined so far
+            self.joined.clear();
+            self.socket = None;
+            return Err(e);
         }
 
         self.base_interface.status = InterfaceStatus::Connected;
         Ok(())
     }
 
-    fn close(&mut self) -> Result<(), String> {
+    fn close(&mut self) -> Result<(), InterfaceError> {
         match self.base_interface.status {
             InterfaceStatus::Disconnected => {
-                self.base_interface.set_error(InterfaceError::NotOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
             }
             _ => {}
         }
-        // Implement UDP connection closing logic here
-        if let Some(ref socket) = self.socket {
-            self.base_interface.status = InterfaceStatus::Disconnected;
-            if self.multicast {
-                if let Some(ref remote_addr) = self.remote_socket_addr {
-                    if remote_addr.is_ipv6() {
-                        socket.leave_multicast_v6(&Ipv6Addr::from_str(&self.remote_addr).unwrap(), 0)
-                            .map_err(|e| e.to_string())?;
-                    }
-                    else {
-                        socket.leave_multicast_v4(&Ipv4Addr::from_str(&self.remote_addr).unwrap(), &Ipv4Addr::new(0, 0, 0, 0))
-                            .map_err(|e| e.to_string())?;
-                    }
-                }
-            }
-            Ok(())
+        if self.socket.is_none() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
         }
-        else {
-            self.base_interface.set_error(InterfaceError::NotOpenIFace);
-            return Err(self.base_interface.error.clone().unwrap().to_string());
-        }
-
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        let result = self.leave_groups(|_| true);
+        self.socket = None;
+        result
     }
 
-    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, String> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
         // Implement UDP reading logic here
         match self.base_interface.get_mode() {
             InterfaceMode::Write => {
-                self.base_interface.set_error(InterfaceError::WriteOnReadOnly);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
             }
             _ => {}
         }
-        if self.socket.is_some() {
-            if let Some(ref socket) = self.socket {
-                let (bytes_read, _) = socket.recv_from(buffer).map_err(|e| e.to_string())?;
-                return Ok(bytes_read as u32);
-            }
-            else {
-                self.base_interface.set_error(InterfaceError::GenericError);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
-            }
-        }
-        else {
-            self.base_interface.set_error(InterfaceError::NotOpenIFace);
-            return Err(self.base_interface.error.clone().unwrap().to_string());
-        }
+        let (bytes_read, _, _) = self.receive(buffer)?;
+        self.base_interface.data_received(bytes_read);
+        Ok(bytes_read as u32)
     }
-
-    fn write(&mut self, buffer: &[u8]) -> Result<(), String> {
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
         // Implement UDP writing logic here
         match self.base_interface.get_mode() {
             InterfaceMode::Read => {
-                self.base_interface.set_error(InterfaceError::ReadOnWriteOnly);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
             }
             _ => {}
         }
-        if let Some(ref remote_addr) = self.remote_socket_addr {
+        if let Some(ref remote_addr) = self.destination() {
             if let Some(ref socket) = self.socket {
-                socket.send_to(buffer, remote_addr).map_err(|e| e.to_string())?;
+                self.base_interface.wait_ready(socket.as_raw_fd(), true)?;
+                socket.send_to(buffer, remote_addr).map_err(|e| self.base_interface.raise_io(e))?;
+                self.base_interface.data_sent(buffer.len());
                 return Ok(());
             }
             else {
-                self.base_interface.set_error(InterfaceError::NotOpenIFace);
-                return Err(self.base_interface.error.clone().unwrap().to_string());
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
             }
         }
         else {
-            self.base_interface.set_error(InterfaceError::GenericError);
-            return Err(self.base_interface.error.clone().unwrap().to_string());
+            return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
         }
     }
 }
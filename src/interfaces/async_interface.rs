@@ -0,0 +1,371 @@
+use std::future::{self, Future};
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use super::reactor::{deregister, poll_io};
+use super::{BaseInterface, FileInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceStatus,
+            InterfaceTrait, UDPInterface};
+
+pub type InterfaceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, InterfaceError>> + Send + 'a>>;
+
+// Async counterpart of InterfaceTrait. The futures only rely on Waker, so any executor can
+// drive them; descriptors are watched by a single shared reactor thread instead of a thread
+// per interface.
+pub trait AsyncInterfaceTrait: Send {
+    fn get_name(&self) -> String;
+    fn get_status(&self) -> InterfaceStatus;
+    fn open(&mut self) -> InterfaceFuture<'_, ()>;
+    fn close(&mut self) -> InterfaceFuture<'_, ()>;
+    fn read<'a>(&'a mut self, buffer: &'a mut [u8]) -> InterfaceFuture<'a, u32>;
+    fn write<'a>(&'a mut self, buffer: &'a [u8]) -> InterfaceFuture<'a, ()>;
+}
+
+// Switches the descriptor of a freshly opened interface to non-blocking mode
+fn set_fd_nonblocking(fd: RawFd) -> std::io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Regular files are always ready, so apart from the open mode checks this mostly matters for
+// pipes and character devices opened through a file path
+pub struct AsyncFileInterface {
+    inner: FileInterface,
+}
+
+impl AsyncFileInterface {
+    pub fn new(inner: FileInterface) -> Self {
+        AsyncFileInterface { inner }
+    }
+
+    pub fn base_interface(&self) -> &BaseInterface {
+        &self.inner.base_interface
+    }
+
+    pub fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.inner.base_interface
+    }
+
+    fn release(&mut self) {
+        if let Some(file) = self.inner.file.as_ref() {
+            deregister(file.as_raw_fd());
+        }
+    }
+}
+
+impl Drop for AsyncFileInterface {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl AsyncInterfaceTrait for AsyncFileInterface {
+    fn get_name(&self) -> String {
+        self.inner.base_interface.get_name()
+    }
+
+    fn get_status(&self) -> InterfaceStatus {
+        self.inner.base_interface.get_status()
+    }
+
+    fn open(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            self.inner.open()?;
+            let fd = self.inner.file.as_ref().unwrap().as_raw_fd();
+            set_fd_nonblocking(fd).map_err(|e| self.inner.base_interface.raise_io(e))
+        })
+    }
+
+    fn close(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            self.release();
+            self.inner.close()
+        })
+    }
+
+    fn read<'a>(&'a mut self, buffer: &'a mut [u8]) -> InterfaceFuture<'a, u32> {
+        Box::pin(async move {
+            if let InterfaceMode::Write = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+            }
+            let file = match self.inner.file.as_mut() {
+                Some(file) => file,
+                None => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            let fd = file.as_raw_fd();
+            let result = future::poll_fn(|cx| poll_io(fd, false, cx, || file.read(buffer))).await;
+            let bytes_read = result.map_err(|e| self.inner.base_interface.raise_io(e))?;
+            self.inner.base_interface.error = None;
+            if bytes_read > 0 {
+                self.inner.base_interface.data_received(bytes_read);
+            }
+            Ok(bytes_read as u32)
+        })
+    }
+
+    fn write<'a>(&'a mut self, buffer: &'a [u8]) -> InterfaceFuture<'a, ()> {
+        Box::pin(async move {
+            if let InterfaceMode::Read = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+            }
+            let file = match self.inner.file.as_mut() {
+                Some(file) => file,
+                None => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            let fd = file.as_raw_fd();
+            let mut written = 0;
+            while written < buffer.len() {
+                let result = future::poll_fn(|cx| poll_io(fd, true, cx, || file.write(&buffer[written..]))).await;
+                written += result.map_err(|e| self.inner.base_interface.raise_io(e))?;
+            }
+            self.inner.base_interface.error = None;
+            self.inner.base_interface.data_sent(buffer.len());
+            Ok(())
+        })
+    }
+}
+
+pub struct AsyncUdpInterface {
+    inner: UDPInterface,
+}
+
+impl AsyncUdpInterface {
+    // Configure the remote address on the blocking interface before handing it over
+    pub fn new(inner: UDPInterface) -> Self {
+        AsyncUdpInterface { inner }
+    }
+
+    pub fn base_interface(&self) -> &BaseInterface {
+        &self.inner.base_interface
+    }
+
+    pub fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.inner.base_interface
+    }
+
+    fn release(&mut self) {
+        if let Some(socket) = self.inner.socket.as_ref() {
+            deregister(socket.as_raw_fd());
+        }
+    }
+}
+
+impl Drop for AsyncUdpInterface {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl AsyncInterfaceTrait for AsyncUdpInterface {
+    fn get_name(&self) -> String {
+        self.inner.base_interface.get_name()
+    }
+
+    fn get_status(&self) -> InterfaceStatus {
+        self.inner.base_interface.get_status()
+    }
+
+    fn open(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            self.inner.open()?;
+            let socket = self.inner.socket.as_ref().unwrap();
+            socket.set_nonblocking(true).map_err(|e| self.inner.base_interface.raise_io(e))
+        })
+    }
+
+    fn close(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            self.release();
+            self.inner.close()
+        })
+    }
+
+    fn read<'a>(&'a mut self, buffer: &'a mut [u8]) -> InterfaceFuture<'a, u32> {
+        Box::pin(async move {
+            if let InterfaceMode::Write = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+            }
+            loop {
+                let socket = match self.inner.socket.as_ref() {
+                    Some(socket) => socket,
+                    None => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+                };
+                let fd = socket.as_raw_fd();
+                let result = future::poll_fn(|cx| poll_io(fd, false, cx, || socket.recv_from(buffer))).await;
+                let (bytes_read, source) = result.map_err(|e| self.inner.base_interface.raise_io(e))?;
+                if self.inner.accepts(&source) {
+                    self.inner.base_interface.data_received(bytes_read);
+                    return Ok(bytes_read as u32);
+                }
+            }
+        })
+    }
+
+    fn write<'a>(&'a mut self, buffer: &'a [u8]) -> InterfaceFuture<'a, ()> {
+        Box::pin(async move {
+            if let InterfaceMode::Read = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+            }
+            let (socket, remote_addr) = match (self.inner.socket.as_ref(), self.inner.destination()) {
+                (Some(socket), Some(remote_addr)) => (socket, remote_addr),
+                (None, _) => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+                (Some(_), None) => return Err(self.inner.base_interface.raise(InterfaceErrorKind::GenericError)),
+            };
+            let fd = socket.as_raw_fd();
+            let result = future::poll_fn(|cx| poll_io(fd, true, cx, || socket.send_to(buffer, remote_addr))).await;
+            result.map_err(|e| self.inner.base_interface.raise_io(e))?;
+            self.inner.base_interface.data_sent(buffer.len());
+            Ok(())
+        })
+    }
+}
+
+struct ReplySlot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    // The job was dropped without running, the worker is gone
+    abandoned: bool,
+}
+
+// Completes a WorkerReply, or abandons it when dropped unused
+struct ReplySender<T> {
+    slot: Arc<Mutex<ReplySlot<T>>>,
+}
+
+impl<T> ReplySender<T> {
+    fn send(self, value: T) {
+        self.slot.lock().unwrap_or_else(|e| e.into_inner()).value = Some(value);
+    }
+}
+
+impl<T> Drop for ReplySender<T> {
+    fn drop(&mut self) {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.value.is_none() {
+            slot.abandoned = true;
+        }
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+// Result of a job run on a BlockingAdapter worker thread
+pub struct WorkerReply<T> {
+    slot: Arc<Mutex<ReplySlot<T>>>,
+    name: String,
+}
+
+impl<T> Future for WorkerReply<T> {
+    type Output = Result<T, InterfaceError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = slot.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+        if slot.abandoned {
+            return Poll::Ready(Err(InterfaceError::with_source(InterfaceErrorKind::GenericError,
+                                                               &self.name,
+                                                               "interface worker thread is gone")));
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+type Job<I> = Box<dyn FnOnce(&mut I) + Send>;
+
+// Runs any blocking interface on a worker thread of its own, queueing the operations in order.
+// Costs a thread per interface, so it is meant for the interfaces that have no native async
+// implementation.
+pub struct BlockingAdapter<I: InterfaceTrait + Send + 'static> {
+    name: String,
+    status: Arc<Mutex<InterfaceStatus>>,
+    jobs: Sender<Job<I>>,
+}
+
+impl<I: InterfaceTrait + Send + 'static> BlockingAdapter<I> {
+    pub fn new(interface: I) -> Result<Self, InterfaceError> {
+        let name = interface.base_interface().get_name();
+        let status = Arc::new(Mutex::new(interface.base_interface().get_status()));
+        let (jobs, receiver) = mpsc::channel::<Job<I>>();
+        let worker_status = status.clone();
+        thread::Builder::new()
+            .name(format!("interface-{}", name))
+            .spawn(move || {
+                let mut interface = interface;
+                // Ends once the adapter is dropped and the queued jobs are done
+                for job in receiver {
+                    job(&mut interface);
+                    *worker_status.lock().unwrap_or_else(|e| e.into_inner()) = interface.base_interface().get_status();
+                }
+            })
+            .map_err(|e| InterfaceError::with_source(InterfaceErrorKind::IoError, &name, e))?;
+        Ok(BlockingAdapter { name, status, jobs })
+    }
+
+    // Queues `job` behind the operations already waiting, e.g. to read statistics or change
+    // settings of the interface
+    pub fn call<T, F>(&self, job: F) -> WorkerReply<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut I) -> T + Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(ReplySlot { value: None, waker: None, abandoned: false }));
+        let sender = ReplySender { slot: slot.clone() };
+        // A failed send drops the job and with it the sender, which abandons the reply
+        let _ = self.jobs.send(Box::new(move |interface: &mut I| sender.send(job(interface))));
+        WorkerReply { slot, name: self.name.clone() }
+    }
+}
+
+impl<I: InterfaceTrait + Send + 'static> AsyncInterfaceTrait for BlockingAdapter<I> {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    // As of the last finished operation
+    fn get_status(&self) -> InterfaceStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn open(&mut self) -> InterfaceFuture<'_, ()> {
+        let reply = self.call(|interface| interface.open());
+        Box::pin(async move { reply.await? })
+    }
+
+    fn close(&mut self) -> InterfaceFuture<'_, ()> {
+        let reply = self.call(|interface| interface.close());
+        Box::pin(async move { reply.await? })
+    }
+
+    fn read<'a>(&'a mut self, buffer: &'a mut [u8]) -> InterfaceFuture<'a, u32> {
+        let length = buffer.len();
+        let reply = self.call(move |interface| {
+            let mut data = vec![0u8; length];
+            interface.read(&mut data).map(|bytes_read| {
+                data.truncate(bytes_read as usize);
+                data
+            })
+        });
+        Box::pin(async move {
+            let data = reply.await??;
+            buffer[..data.len()].copy_from_slice(&data);
+            Ok(data.len() as u32)
+        })
+    }
+
+    fn write<'a>(&'a mut self, buffer: &'a [u8]) -> InterfaceFuture<'a, ()> {
+        let data = buffer.to_vec();
+        let reply = self.call(move |interface| interface.write(&data));
+        Box::pin(async move { reply.await? })
+    }
+}
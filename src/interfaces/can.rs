@@ -0,0 +1,440 @@
+use std::ffi::CString;
+use std::io::ErrorKind;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface};
+
+// Frame lengths a CAN FD frame can carry, anything in between is padded up by the controller
+const CANFD_LENGTHS: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanFrame {
+    id: u32,
+    extended: bool,
+    remote: bool,
+    error: bool,
+    fd: bool,
+    bitrate_switch: bool,
+    data: Vec<u8>,
+}
+
+impl CanFrame {
+    fn valid_id(id: u32, extended: bool) -> bool {
+        if extended { id <= libc::CAN_EFF_MASK } else { id <= libc::CAN_SFF_MASK }
+    }
+
+    pub fn new(id: u32, extended: bool, data: &[u8]) -> Option<CanFrame> {
+        if !CanFrame::valid_id(id, extended) || data.len() > libc::CAN_MAX_DLEN {
+            return None;
+        }
+        Some(CanFrame { id, extended, remote: false, error: false, fd: false, bitrate_switch: false, data: data.to_vec() })
+    }
+
+    pub fn new_remote(id: u32, extended: bool, dlc: usize) -> Option<CanFrame> {
+        if !CanFrame::valid_id(id, extended) || dlc > libc::CAN_MAX_DLEN {
+            return None;
+        }
+        Some(CanFrame { id, extended, remote: true, error: false, fd: false, bitrate_switch: false, data: vec![0; dlc] })
+    }
+
+    pub fn new_fd(id: u32, extended: bool, data: &[u8], bitrate_switch: bool) -> Option<CanFrame> {
+        if !CanFrame::valid_id(id, extended) || !CANFD_LENGTHS.contains(&data.len()) {
+            return None;
+        }
+        Some(CanFrame { id, extended, remote: false, error: false, fd: true, bitrate_switch, data: data.to_vec() })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error
+    }
+
+    pub fn is_fd(&self) -> bool {
+        self.fd
+    }
+
+    pub fn is_bitrate_switch(&self) -> bool {
+        self.bitrate_switch
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn raw_id(&self) -> u32 {
+        let mut raw_id = self.id;
+        if self.extended {
+            raw_id |= libc::CAN_EFF_FLAG;
+        }
+        if self.remote {
+            raw_id |= libc::CAN_RTR_FLAG;
+        }
+        if self.error {
+            raw_id |= libc::CAN_ERR_FLAG;
+        }
+        raw_id
+    }
+
+    // Kernel struct can_frame (CAN_MTU bytes) or struct canfd_frame (CANFD_MTU bytes)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; if self.fd { libc::CANFD_MTU } else { libc::CAN_MTU }];
+        bytes[0..4].copy_from_slice(&self.raw_id().to_ne_bytes());
+        bytes[4] = self.data.len() as u8;
+        if self.fd {
+            bytes[5] = libc::CANFD_FDF as u8;
+            if self.bitrate_switch {
+                bytes[5] |= libc::CANFD_BRS as u8;
+            }
+        }
+        if !self.remote {
+            bytes[8..8 + self.data.len()].copy_from_slice(&self.data);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<CanFrame> {
+        let fd = match bytes.len() {
+            libc::CAN_MTU => false,
+            libc::CANFD_MTU => true,
+            _ => return None,
+        };
+        let raw_id = u32::from_ne_bytes(bytes[0..4].try_into().unwrap());
+        let extended = raw_id & libc::CAN_EFF_FLAG != 0;
+        let len = bytes[4] as usize;
+        let max_len = if fd { libc::CANFD_MAX_DLEN } else { libc::CAN_MAX_DLEN };
+        if len > max_len {
+            return None;
+        }
+        let remote = !fd && raw_id & libc::CAN_RTR_FLAG != 0;
+        Some(CanFrame {
+            id: raw_id & if extended { libc::CAN_EFF_MASK } else { libc::CAN_SFF_MASK },
+            extended,
+            remote,
+            error: raw_id & libc::CAN_ERR_FLAG != 0,
+            fd,
+            bitrate_switch: fd && bytes[5] & libc::CANFD_BRS as u8 != 0,
+            data: if remote { vec![0; len] } else { bytes[8..8 + len].to_vec() },
+        })
+    }
+}
+
+// Accepts frames for which (received_id & mask) == (id & mask), or the opposite when inverted
+#[derive(Clone, Debug)]
+pub struct CanFilter {
+    pub id: u32,
+    pub mask: u32,
+    pub extended: bool,
+    pub inverted: bool,
+}
+
+impl CanFilter {
+    pub fn new(id: u32, mask: u32, extended: bool) -> Self {
+        CanFilter { id, mask, extended, inverted: false }
+    }
+
+    fn raw(&self) -> [u32; 2] {
+        let mut can_id = self.id;
+        if self.extended {
+            can_id |= libc::CAN_EFF_FLAG;
+        }
+        if self.inverted {
+            can_id |= libc::CAN_INV_FILTER;
+        }
+        // Standard and extended frames never match each other's filters
+        [can_id, self.mask | libc::CAN_EFF_FLAG]
+    }
+}
+
+// Error frames carry the error class in the id and the details in the payload (linux/can/error.h)
+fn error_frame_kind(frame: &CanFrame) -> Option<InterfaceErrorKind> {
+    let class = frame.id();
+    let detail = |index: usize| frame.data().get(index).copied().unwrap_or(0) as libc::c_int;
+    if class & libc::CAN_ERR_BUSOFF != 0 {
+        return Some(InterfaceErrorKind::ConnectionLost);
+    }
+    if class & libc::CAN_ERR_TX_TIMEOUT != 0 {
+        return Some(InterfaceErrorKind::Timeout);
+    }
+    if class & libc::CAN_ERR_CRTL != 0
+        && detail(1) & (libc::CAN_ERR_CRTL_RX_OVERFLOW | libc::CAN_ERR_CRTL_TX_OVERFLOW) != 0 {
+        return Some(InterfaceErrorKind::Overflow);
+    }
+    if class & libc::CAN_ERR_PROT != 0 {
+        if detail(3) == libc::CAN_ERR_PROT_LOC_CRC_SEQ || detail(3) == libc::CAN_ERR_PROT_LOC_CRC_DEL {
+            return Some(InterfaceErrorKind::ChecksumError);
+        }
+        if detail(2) & (libc::CAN_ERR_PROT_FORM | libc::CAN_ERR_PROT_STUFF | libc::CAN_ERR_PROT_BIT
+                        | libc::CAN_ERR_PROT_BIT0 | libc::CAN_ERR_PROT_BIT1) != 0 {
+            return Some(InterfaceErrorKind::FramingError);
+        }
+        return Some(InterfaceErrorKind::ProtocolError);
+    }
+    if class & (libc::CAN_ERR_ACK | libc::CAN_ERR_TRX | libc::CAN_ERR_BUSERROR) != 0 {
+        return Some(InterfaceErrorKind::ProtocolError);
+    }
+    // Lost arbitration, error counter and state warnings are informational
+    None
+}
+
+pub struct CanInterface {
+    if_name: String,
+    socket: Option<OwnedFd>,
+    fd_frames: bool,
+    error_frames: bool,
+    filters: Vec<CanFilter>,
+    base_interface: BaseInterface,
+}
+
+impl CanInterface {
+    pub fn new(name: String, description: String, if_name: String, log_if: Option<bool>) -> Self {
+        CanInterface {
+            if_name,
+            socket: None,
+            fd_frames: false,
+            error_frames: true,
+            filters: Vec::new(),
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::CAN, logic: LogicalInterface::Socket},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn set_fd_frames(&mut self, fd_frames: bool) -> Result<(), InterfaceError> {
+        self.fd_frames = fd_frames;
+        self.apply_socket_options()
+    }
+
+    pub fn set_error_frames(&mut self, error_frames: bool) -> Result<(), InterfaceError> {
+        self.error_frames = error_frames;
+        self.apply_socket_options()
+    }
+
+    // No filters means every frame on the bus is received
+    pub fn set_filters(&mut self, filters: Vec<CanFilter>) -> Result<(), InterfaceError> {
+        self.filters = filters;
+        self.apply_socket_options()
+    }
+
+    fn set_socket_option<T>(&mut self, fd: libc::c_int, option: libc::c_int, value: &[T]) -> Result<(), InterfaceError> {
+        let ret = unsafe {
+            libc::setsockopt(fd, libc::SOL_CAN_RAW, option,
+                             value.as_ptr() as *const libc::c_void,
+                             std::mem::size_of_val(value) as libc::socklen_t)
+        };
+        if ret != 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn apply_socket_options(&mut self) -> Result<(), InterfaceError> {
+        let fd = match self.socket.as_ref() {
+            Some(socket) => socket.as_raw_fd(),
+            None => return Ok(()),
+        };
+        let fd_frames: libc::c_int = self.fd_frames as libc::c_int;
+        self.set_socket_option(fd, libc::CAN_RAW_FD_FRAMES, &[fd_frames])?;
+        let error_mask: libc::can_err_mask_t = if self.error_frames { libc::CAN_ERR_MASK } else { 0 };
+        self.set_socket_option(fd, libc::CAN_RAW_ERR_FILTER, &[error_mask])?;
+        if self.filters.is_empty() {
+            let accept_all = [0u32, 0u32];
+            self.set_socket_option(fd, libc::CAN_RAW_FILTER, &accept_all)?;
+        } else {
+            let raw_filters: Vec<u32> = self.filters.iter().flat_map(|filter| filter.raw()).collect();
+            self.set_socket_option(fd, libc::CAN_RAW_FILTER, &raw_filters)?;
+        }
+        Ok(())
+    }
+
+    fn socket_fd(&mut self) -> Result<libc::c_int, InterfaceError> {
+        match (self.base_interface.get_status(), self.socket.as_ref()) {
+            (InterfaceStatus::Connected, Some(socket)) => Ok(socket.as_raw_fd()),
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+
+    // Error frames are turned into the matching InterfaceErrorKind instead of being returned
+    pub fn read_frame(&mut self) -> Result<CanFrame, InterfaceError> {
+        let fd = self.socket_fd()?;
+        let mut bytes = [0u8; libc::CANFD_MTU];
+        loop {
+            self.base_interface.wait_ready(fd, false)?;
+            let ret = unsafe { libc::read(fd, bytes.as_mut_ptr() as *mut libc::c_void, bytes.len()) };
+            if ret < 0 {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(self.base_interface.raise_io(e));
+            }
+            let frame = match CanFrame::from_bytes(&bytes[..ret as usize]) {
+                Some(frame) => frame,
+                None => {
+                    return Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError));
+                }
+            };
+            if !frame.is_error() {
+                self.base_interface.error = None;
+                self.base_interface.data_received(frame.data().len());
+                return Ok(frame);
+            }
+            if frame.id() & libc::CAN_ERR_RESTARTED != 0 {
+                self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+            }
+            if let Some(error) = error_frame_kind(&frame) {
+                let event = match error {
+                    InterfaceErrorKind::ConnectionLost => InterfaceEvent::ConnectionLost,
+                    _ => InterfaceEvent::ErrorOccurred,
+                };
+                self.base_interface.set_event(event);
+                return Err(self.base_interface.raise(error));
+            }
+        }
+    }
+
+    // Ok(None) when no frame arrived within `timeout`
+    pub fn read_frame_timeout(&mut self, timeout: Duration) -> Result<Option<CanFrame>, InterfaceError> {
+        let fd = self.socket_fd()?;
+        let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        let ret = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+        if ret < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == ErrorKind::Interrupted {
+                return Ok(None);
+            }
+            return Err(self.base_interface.raise_io(e));
+        }
+        if ret == 0 {
+            return Ok(None);
+        }
+        self.read_frame().map(Some)
+    }
+
+    pub fn write_frame(&mut self, frame: &CanFrame) -> Result<(), InterfaceError> {
+        let fd = self.socket_fd()?;
+        if frame.is_fd() && !self.fd_frames {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError));
+        }
+        let bytes = frame.to_bytes();
+        loop {
+            self.base_interface.wait_ready(fd, true)?;
+            let ret = unsafe { libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len()) };
+            if ret == bytes.len() as isize {
+                break;
+            }
+            if ret >= 0 {
+                return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
+            }
+            let e = std::io::Error::last_os_error();
+            match e.kind() {
+                ErrorKind::Interrupted => {}
+                // ENOBUFS: the transmit queue of the device is full
+                _ if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+                }
+                _ => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_sent(frame.data().len());
+        Ok(())
+    }
+}
+
+impl InterfaceTrait for CanInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.socket.iter().map(|socket| socket.as_raw_fd()).collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let if_name = CString::new(self.if_name.as_str())
+            .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+        let if_index = unsafe { libc::if_nametoindex(if_name.as_ptr()) };
+        if if_index == 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        let raw_fd = unsafe { libc::socket(libc::PF_CAN, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::CAN_RAW) };
+        if raw_fd < 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+        let mut addr: libc::sockaddr_can = unsafe { std::mem::zeroed() };
+        addr.can_family = libc::AF_CAN as libc::sa_family_t;
+        addr.can_ifindex = if_index as libc::c_int;
+        let ret = unsafe {
+            libc::bind(socket.as_raw_fd(),
+                       &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                       std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t)
+        };
+        if ret != 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        self.socket = Some(socket);
+        if let Err(e) = self.apply_socket_options() {
+            self.socket = None;
+            return Err(e);
+        }
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.socket = None;
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    // The buffer receives the kernel can_frame / canfd_frame layout, see CanFrame::from_bytes
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let frame = self.read_frame()?;
+        let bytes = frame.to_bytes();
+        if bytes.len() > buffer.len() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..bytes.len()].copy_from_slice(&bytes);
+        Ok(bytes.len() as u32)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        match CanFrame::from_bytes(buffer) {
+            Some(frame) => self.write_frame(&frame),
+            None => {
+                Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError))
+            }
+        }
+    }
+}
@@ -0,0 +1,727 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::os::fd::RawFd;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::processor_base::processing::DataProcessor;
+use super::can::{CanFrame, CanInterface};
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface};
+
+// Function codes of the predefined connection set (CiA 301)
+const COB_NMT: u32 = 0x000;
+const COB_EMCY: u32 = 0x080;
+const COB_SDO_TX: u32 = 0x580;
+const COB_SDO_RX: u32 = 0x600;
+const COB_HEARTBEAT: u32 = 0x700;
+
+const SDO_ABORT: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NmtCommand {
+    Start = 0x01,
+    Stop = 0x02,
+    EnterPreOperational = 0x80,
+    ResetNode = 0x81,
+    ResetCommunication = 0x82,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NmtState {
+    BootUp,
+    Stopped,
+    Operational,
+    PreOperational,
+    Unknown(u8),
+}
+
+impl NmtState {
+    pub fn from_heartbeat(state: u8) -> NmtState {
+        match state & 0x7F {
+            0x00 => NmtState::BootUp,
+            0x04 => NmtState::Stopped,
+            0x05 => NmtState::Operational,
+            0x7F => NmtState::PreOperational,
+            other => NmtState::Unknown(other),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanOpenDataType {
+    Boolean,
+    Integer8,
+    Integer16,
+    Integer32,
+    Integer64,
+    Unsigned8,
+    Unsigned16,
+    Unsigned32,
+    Unsigned64,
+    Real32,
+    Real64,
+    VisibleString,
+    OctetString,
+    Domain,
+}
+
+impl CanOpenDataType {
+    pub fn from_code(code: u16) -> Option<CanOpenDataType> {
+        match code {
+            0x0001 => Some(CanOpenDataType::Boolean),
+            0x0002 => Some(CanOpenDataType::Integer8),
+            0x0003 => Some(CanOpenDataType::Integer16),
+            0x0004 => Some(CanOpenDataType::Integer32),
+            0x0005 => Some(CanOpenDataType::Unsigned8),
+            0x0006 => Some(CanOpenDataType::Unsigned16),
+            0x0007 => Some(CanOpenDataType::Unsigned32),
+            0x0008 => Some(CanOpenDataType::Real32),
+            0x0009 => Some(CanOpenDataType::VisibleString),
+            0x000A => Some(CanOpenDataType::OctetString),
+            0x000F => Some(CanOpenDataType::Domain),
+            0x0011 => Some(CanOpenDataType::Real64),
+            0x0015 => Some(CanOpenDataType::Integer64),
+            0x001B => Some(CanOpenDataType::Unsigned64),
+            _ => None,
+        }
+    }
+
+    // Size in bits of fixed size types, None for strings and domains
+    pub fn bit_length(&self) -> Option<u8> {
+        match self {
+            CanOpenDataType::Boolean => Some(1),
+            CanOpenDataType::Integer8 | CanOpenDataType::Unsigned8 => Some(8),
+            CanOpenDataType::Integer16 | CanOpenDataType::Unsigned16 => Some(16),
+            CanOpenDataType::Integer32 | CanOpenDataType::Unsigned32 | CanOpenDataType::Real32 => Some(32),
+            CanOpenDataType::Integer64 | CanOpenDataType::Unsigned64 | CanOpenDataType::Real64 => Some(64),
+            CanOpenDataType::VisibleString | CanOpenDataType::OctetString | CanOpenDataType::Domain => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+    Constant,
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectEntry {
+    pub name: String,
+    pub data_type: CanOpenDataType,
+    pub access: ObjectAccess,
+    pub default_value: Option<String>,
+    pub pdo_mappable: bool,
+}
+
+// Object dictionary of a remote node, keyed by (index, subindex)
+#[derive(Clone, Debug, Default)]
+pub struct ObjectDictionary {
+    entries: BTreeMap<(u16, u8), ObjectEntry>,
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse::<u32>().ok()
+    }
+}
+
+impl ObjectDictionary {
+    pub fn new() -> Self {
+        ObjectDictionary { entries: BTreeMap::new() }
+    }
+
+    pub fn insert(&mut self, index: u16, subindex: u8, entry: ObjectEntry) {
+        self.entries.insert((index, subindex), entry);
+    }
+
+    pub fn get(&self, index: u16, subindex: u8) -> Option<&ObjectEntry> {
+        self.entries.get(&(index, subindex))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&(u16, u8), &ObjectEntry)> {
+        self.entries.iter()
+    }
+
+    // Reads the object sections ([1000], [1018sub1], ...) of a CiA 306 EDS/DCF file;
+    // sections without a DataType (records, file and device info) are skipped
+    pub fn from_eds(text: &str) -> Result<ObjectDictionary, String> {
+        let mut dictionary = ObjectDictionary::new();
+        let mut sections: Vec<(String, HashMap<String, String>)> = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                sections.push((section.trim().to_lowercase(), HashMap::new()));
+            } else if let Some((key, value)) = line.split_once('=') {
+                match sections.last_mut() {
+                    Some((_, keys)) => {
+                        keys.insert(key.trim().to_lowercase(), value.trim().to_string());
+                    }
+                    None => return Err(format!("EDS line {}: key outside of a section", line_number + 1)),
+                }
+            } else {
+                return Err(format!("EDS line {}: cannot parse '{}'", line_number + 1, line));
+            }
+        }
+        for (section, keys) in sections {
+            let (index_text, subindex_text) = match section.split_once("sub") {
+                Some((index_text, subindex_text)) => (index_text, subindex_text),
+                None => (section.as_str(), "0"),
+            };
+            let index = match u16::from_str_radix(index_text, 16) {
+                Ok(index) if index_text.len() == 4 => index,
+                _ => continue,
+            };
+            let subindex = u8::from_str_radix(subindex_text, 16)
+                .map_err(|_| format!("EDS section [{}]: invalid subindex", section))?;
+            let data_type = match keys.get("datatype") {
+                Some(code) => parse_number(code)
+                    .and_then(|code| CanOpenDataType::from_code(code as u16))
+                    .ok_or(format!("EDS section [{}]: unsupported DataType {}", section, code))?,
+                None => continue,
+            };
+            let access = match keys.get("accesstype").map(|access| access.to_lowercase()).as_deref() {
+                Some("ro") => ObjectAccess::ReadOnly,
+                Some("wo") => ObjectAccess::WriteOnly,
+                Some("const") => ObjectAccess::Constant,
+                Some("rw") | Some("rww") | Some("rwr") | None => ObjectAccess::ReadWrite,
+                Some(other) => return Err(format!("EDS section [{}]: unknown AccessType {}", section, other)),
+            };
+            dictionary.insert(index, subindex, ObjectEntry {
+                name: keys.get("parametername").cloned().unwrap_or_default(),
+                data_type,
+                access,
+                default_value: keys.get("defaultvalue").cloned(),
+                pdo_mappable: keys.get("pdomapping").and_then(|value| parse_number(value)).unwrap_or(0) != 0,
+            });
+        }
+        Ok(dictionary)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PdoMappingEntry {
+    pub index: u16,
+    pub subindex: u8,
+    pub bit_length: u8,
+}
+
+impl PdoMappingEntry {
+    // Value of the 0x1A00/0x1600 mapping parameter subentries
+    pub fn from_mapping_parameter(value: u32) -> Self {
+        PdoMappingEntry {
+            index: (value >> 16) as u16,
+            subindex: (value >> 8) as u8,
+            bit_length: value as u8,
+        }
+    }
+}
+
+struct HeartbeatConsumer {
+    timeout: Duration,
+    last_seen: Option<Instant>,
+    state: Option<NmtState>,
+    expired: bool,
+}
+
+// CANopen master on top of a CanInterface: NMT, heartbeat consumer, SDO client and PDO decoding.
+// Decoded PDO values come out as DataProcessor frames whose ifcode is (index << 8 | subindex)
+// of the mapped object and whose id counts the receptions of that PDO.
+pub struct CanOpenInterface {
+    can: CanInterface,
+    sdo_timeout: Duration,
+    heartbeats: BTreeMap<u8, HeartbeatConsumer>,
+    dictionaries: HashMap<u8, ObjectDictionary>,
+    pdo_mappings: HashMap<u32, Vec<PdoMappingEntry>>,
+    pdo_counters: HashMap<u32, u64>,
+    received: VecDeque<DataProcessor>,
+    last_emergency: Option<(u8, Vec<u8>)>,
+    base_interface: BaseInterface,
+}
+
+impl CanOpenInterface {
+    pub fn new(name: String, description: String, can: CanInterface, log_if: Option<bool>) -> Self {
+        CanOpenInterface {
+            can,
+            sdo_timeout: Duration::from_millis(500),
+            heartbeats: BTreeMap::new(),
+            dictionaries: HashMap::new(),
+            pdo_mappings: HashMap::new(),
+            pdo_counters: HashMap::new(),
+            received: VecDeque::new(),
+            last_emergency: None,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::CAN, logic: LogicalInterface::Socket},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::CANopen,
+                                            log_if),
+        }
+    }
+
+    pub fn set_sdo_timeout(&mut self, sdo_timeout: Duration) {
+        self.sdo_timeout = sdo_timeout;
+    }
+
+    // Describes the objects of a node, used to size PDO mappings and to refuse invalid SDO accesses
+    pub fn set_object_dictionary(&mut self, node_id: u8, dictionary: ObjectDictionary) -> Result<(), InterfaceError> {
+        self.check_node_id(node_id)?;
+        self.dictionaries.insert(node_id, dictionary);
+        Ok(())
+    }
+
+    pub fn object_dictionary(&self, node_id: u8) -> Option<&ObjectDictionary> {
+        self.dictionaries.get(&node_id)
+    }
+
+    fn check_access(&mut self, node_id: u8, index: u16, subindex: u8, write: bool) -> Result<(), InterfaceError> {
+        let access = match self.dictionaries.get(&node_id).and_then(|dictionary| dictionary.get(index, subindex)) {
+            Some(entry) => entry.access,
+            None => return Ok(()),
+        };
+        let allowed = match access {
+            ObjectAccess::ReadWrite => true,
+            ObjectAccess::ReadOnly | ObjectAccess::Constant => !write,
+            ObjectAccess::WriteOnly => write,
+        };
+        if !allowed {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       format!("Object {:04X}:{:02X} of node {} is not {}",
+                                                               index, subindex, node_id, if write { "writable" } else { "readable" })));
+        }
+        Ok(())
+    }
+
+    fn check_node_id(&mut self, node_id: u8) -> Result<(), InterfaceError> {
+        if node_id == 0 || node_id > 127 {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       format!("Invalid CANopen node id {}", node_id)));
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, cob_id: u32, data: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        match CanFrame::new(cob_id, false, data) {
+            Some(frame) => self.can.write_frame(&frame),
+            None => {
+                Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError))
+            }
+        }
+    }
+
+    // node_id 0 addresses every node on the bus
+    pub fn send_nmt(&mut self, command: NmtCommand, node_id: u8) -> Result<(), InterfaceError> {
+        if node_id != 0 {
+            self.check_node_id(node_id)?;
+        }
+        self.send(COB_NMT, &[command as u8, node_id])?;
+        self.base_interface.data_sent(2);
+        Ok(())
+    }
+
+    pub fn send_pdo(&mut self, cob_id: u32, data: &[u8]) -> Result<(), InterfaceError> {
+        self.send(cob_id, data)?;
+        self.base_interface.data_sent(data.len());
+        Ok(())
+    }
+
+    pub fn add_heartbeat_consumer(&mut self, node_id: u8, timeout: Duration) -> Result<(), InterfaceError> {
+        self.check_node_id(node_id)?;
+        self.heartbeats.insert(node_id, HeartbeatConsumer { timeout, last_seen: None, state: None, expired: false });
+        Ok(())
+    }
+
+    // Last NMT state reported by the node's heartbeat
+    pub fn node_state(&self, node_id: u8) -> Option<NmtState> {
+        self.heartbeats.get(&node_id).and_then(|consumer| consumer.state)
+    }
+
+    // Nodes whose heartbeat is overdue, ConnectionLost is raised once per expiry
+    pub fn check_heartbeats(&mut self) -> Vec<u8> {
+        let now = Instant::now();
+        let mut expired_nodes = Vec::new();
+        let mut newly_expired = false;
+        for (node_id, consumer) in self.heartbeats.iter_mut() {
+            if consumer.last_seen.is_some_and(|last_seen| now.duration_since(last_seen) > consumer.timeout) {
+                expired_nodes.push(*node_id);
+                newly_expired |= !consumer.expired;
+                consumer.expired = true;
+            }
+        }
+        if newly_expired {
+            self.base_interface.set_event(InterfaceEvent::ConnectionLost);
+        }
+        expired_nodes
+    }
+
+    // Node id and manufacturer data of the last EMCY message
+    pub fn last_emergency(&self) -> Option<(u8, Vec<u8>)> {
+        self.last_emergency.clone()
+    }
+
+    pub fn map_pdo(&mut self, cob_id: u32, entries: Vec<PdoMappingEntry>) -> Result<(), InterfaceError> {
+        let total_bits: u32 = entries.iter().map(|entry| entry.bit_length as u32).sum();
+        if total_bits > 64 || entries.iter().any(|entry| entry.bit_length == 0) {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       format!("PDO mapping of 0x{:03X} does not fit in a CAN frame",
+                                                               cob_id)));
+        }
+        self.pdo_mappings.insert(cob_id, entries);
+        Ok(())
+    }
+
+    // Maps a PDO from the object dictionary of the node, bit lengths follow the object data types
+    pub fn map_pdo_objects(&mut self, node_id: u8, cob_id: u32, objects: &[(u16, u8)]) -> Result<(), InterfaceError> {
+        let mut entries = Vec::with_capacity(objects.len());
+        for (index, subindex) in objects {
+            let bit_length = self.dictionaries.get(&node_id)
+                .and_then(|dictionary| dictionary.get(*index, *subindex))
+                .and_then(|entry| entry.data_type.bit_length());
+            match bit_length {
+                Some(bit_length) => entries.push(PdoMappingEntry { index: *index, subindex: *subindex, bit_length }),
+                None => {
+                    return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                               format!("Object {:04X}:{:02X} of node {} cannot be mapped",
+                                                                       index, subindex, node_id)));
+                }
+            }
+        }
+        self.map_pdo(cob_id, entries)
+    }
+
+    pub fn unmap_pdo(&mut self, cob_id: u32) {
+        self.pdo_mappings.remove(&cob_id);
+        self.pdo_counters.remove(&cob_id);
+    }
+
+    // Reads the COB-ID (0x1800 + n) and mapping (0x1A00 + n) of transmit PDO n of a node over SDO
+    pub fn map_tpdo_from_node(&mut self, node_id: u8, pdo_number: u16) -> Result<u32, InterfaceError> {
+        if pdo_number > 511 {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       format!("Invalid TPDO number {}", pdo_number)));
+        }
+        let cob_id = self.upload_u32(node_id, 0x1800 + pdo_number, 1)? & 0x7FF;
+        let mapping_count = self.upload(node_id, 0x1A00 + pdo_number, 0)?.first().copied().unwrap_or(0);
+        let mut entries = Vec::with_capacity(mapping_count as usize);
+        for subindex in 1..=mapping_count {
+            let value = self.upload_u32(node_id, 0x1A00 + pdo_number, subindex)?;
+            entries.push(PdoMappingEntry::from_mapping_parameter(value));
+        }
+        self.map_pdo(cob_id, entries)?;
+        Ok(cob_id)
+    }
+
+    fn upload_u32(&mut self, node_id: u8, index: u16, subindex: u8) -> Result<u32, InterfaceError> {
+        let data = self.upload(node_id, index, subindex)?;
+        let mut bytes = [0u8; 4];
+        let len = data.len().min(4);
+        bytes[..len].copy_from_slice(&data[..len]);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn decode_pdo(&mut self, cob_id: u32, data: &[u8]) {
+        let entries = match self.pdo_mappings.get(&cob_id) {
+            Some(entries) => entries.clone(),
+            None => return,
+        };
+        let mut raw = [0u8; 8];
+        raw[..data.len().min(8)].copy_from_slice(&data[..data.len().min(8)]);
+        let raw = u64::from_le_bytes(raw);
+        let counter = self.pdo_counters.entry(cob_id).or_insert(0);
+        *counter += 1;
+        let sequence = *counter;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut bit_offset: u32 = 0;
+        for entry in entries {
+            // A short frame cannot carry this object, the rest of the mapping is lost as well
+            if bit_offset + entry.bit_length as u32 > data.len() as u32 * 8 {
+                self.base_interface.set_error(InterfaceErrorKind::Underflow);
+                return;
+            }
+            let mask = if entry.bit_length >= 64 { u64::MAX } else { (1u64 << entry.bit_length) - 1 };
+            let value = (raw >> bit_offset) & mask;
+            let byte_length = (entry.bit_length as usize).div_ceil(8);
+            self.received.push_back(DataProcessor::new(
+                ((entry.index as u64) << 8) | entry.subindex as u64,
+                sequence,
+                now.as_secs(),
+                now.subsec_nanos() as u64,
+                byte_length as u64,
+                value.to_le_bytes()[..byte_length].to_vec()));
+            bit_offset += entry.bit_length as u32;
+        }
+    }
+
+    // Routes heartbeat, emergency and PDO frames; SDO responses are consumed by the transfers
+    fn dispatch(&mut self, frame: &CanFrame) {
+        if frame.is_extended() || frame.is_remote() {
+            return;
+        }
+        let cob_id = frame.id();
+        let data = frame.data();
+        let node_id = (cob_id & 0x7F) as u8;
+        match cob_id & 0x780 {
+            COB_HEARTBEAT if node_id != 0 && !data.is_empty() => {
+                let state = NmtState::from_heartbeat(data[0]);
+                if let Some(consumer) = self.heartbeats.get_mut(&node_id) {
+                    consumer.last_seen = Some(Instant::now());
+                    consumer.state = Some(state);
+                    consumer.expired = false;
+                }
+                if let NmtState::BootUp = state {
+                    self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+                }
+            }
+            COB_EMCY if node_id != 0 && !self.pdo_mappings.contains_key(&cob_id) => {
+                self.last_emergency = Some((node_id, data.to_vec()));
+                self.base_interface.set_event(InterfaceEvent::ErrorOccurred);
+            }
+            _ => {
+                if self.pdo_mappings.contains_key(&cob_id) {
+                    self.decode_pdo(cob_id, data);
+                    self.base_interface.data_received(data.len());
+                }
+            }
+        }
+    }
+
+    // Reads and dispatches bus traffic for up to `timeout`
+    pub fn process(&mut self, timeout: Duration) -> Result<(), InterfaceError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.can.read_frame_timeout(remaining)? {
+                Some(frame) => self.dispatch(&frame),
+                None => {
+                    if remaining.is_zero() || Instant::now() >= deadline {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    // Waits, within the read timeout, until the next mapped PDO object value is available
+    pub fn read_pdo(&mut self) -> Result<DataProcessor, InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(data) = self.received.pop_front() {
+                self.base_interface.error = None;
+                return Ok(data);
+            }
+            let frame = match deadline {
+                Some(deadline) => {
+                    match self.can.read_frame_timeout(deadline.saturating_duration_since(Instant::now()))? {
+                        Some(frame) => frame,
+                        None => return Err(self.base_interface.timed_out()),
+                    }
+                }
+                None => self.can.read_frame()?,
+            };
+            self.dispatch(&frame);
+        }
+    }
+
+    fn sdo_request(&mut self, node_id: u8, index: u16, subindex: u8, request: [u8; 8]) -> Result<[u8; 8], InterfaceError> {
+        self.send(COB_SDO_RX + node_id as u32, &request)?;
+        let deadline = Instant::now() + self.sdo_timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(self.base_interface.raise(InterfaceErrorKind::Timeout));
+            }
+            let frame = match self.can.read_frame_timeout(remaining)? {
+                Some(frame) => frame,
+                None => continue,
+            };
+            if frame.id() != COB_SDO_TX + node_id as u32 || frame.is_extended() || frame.data().len() != 8 {
+                self.dispatch(&frame);
+                continue;
+            }
+            let mut response = [0u8; 8];
+            response.copy_from_slice(frame.data());
+            if response[0] == SDO_ABORT {
+                let abort_code = u32::from_le_bytes(response[4..8].try_into().unwrap());
+                return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                           format!("SDO abort 0x{:08X} on node {} object {:04X}:{:02X}",
+                                                                   abort_code, node_id, index, subindex)));
+            }
+            return Ok(response);
+        }
+    }
+
+    fn sdo_abort(&mut self, node_id: u8, index: u16, subindex: u8, abort_code: u32) -> InterfaceError {
+        let mut request = [SDO_ABORT, index as u8, (index >> 8) as u8, subindex, 0, 0, 0, 0];
+        request[4..8].copy_from_slice(&abort_code.to_le_bytes());
+        let _ = self.send(COB_SDO_RX + node_id as u32, &request);
+        self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                       format!("SDO transfer of node {} object {:04X}:{:02X} aborted (0x{:08X})",
+                                               node_id, index, subindex, abort_code))
+    }
+
+    // SDO upload (read an object of a node), expedited or segmented as the server chooses
+    pub fn upload(&mut self, node_id: u8, index: u16, subindex: u8) -> Result<Vec<u8>, InterfaceError> {
+        self.check_node_id(node_id)?;
+        self.check_access(node_id, index, subindex, false)?;
+        let request = [0x40, index as u8, (index >> 8) as u8, subindex, 0, 0, 0, 0];
+        let response = self.sdo_request(node_id, index, subindex, request)?;
+        if response[0] & 0xE0 != 0x40 {
+            return Err(self.sdo_abort(node_id, index, subindex, 0x0504_0001));
+        }
+        let expedited = response[0] & 0x02 != 0;
+        let size_indicated = response[0] & 0x01 != 0;
+        if expedited {
+            let len = if size_indicated { 4 - ((response[0] >> 2) & 0x03) as usize } else { 4 };
+            self.base_interface.error = None;
+            self.base_interface.data_received(len);
+            return Ok(response[4..4 + len].to_vec());
+        }
+        let total_size = if size_indicated {
+            Some(u32::from_le_bytes(response[4..8].try_into().unwrap()) as usize)
+        } else {
+            None
+        };
+        let mut data = Vec::with_capacity(total_size.unwrap_or(0));
+        let mut toggle = 0u8;
+        loop {
+            let request = [0x60 | toggle, 0, 0, 0, 0, 0, 0, 0];
+            let response = self.sdo_request(node_id, index, subindex, request)?;
+            if response[0] & 0xE0 != 0x00 || response[0] & 0x10 != toggle {
+                return Err(self.sdo_abort(node_id, index, subindex, 0x0503_0000));
+            }
+            let unused = ((response[0] >> 1) & 0x07) as usize;
+            data.extend_from_slice(&response[1..8 - unused]);
+            if response[0] & 0x01 != 0 {
+                break;
+            }
+            toggle ^= 0x10;
+        }
+        if total_size.is_some_and(|total_size| total_size != data.len()) {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       format!("SDO upload of {:04X}:{:02X} returned {} bytes instead of {}",
+                                                               index, subindex, data.len(), total_size.unwrap())));
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_received(data.len());
+        Ok(data)
+    }
+
+    // SDO download (write an object of a node), expedited up to 4 bytes, segmented above
+    pub fn download(&mut self, node_id: u8, index: u16, subindex: u8, data: &[u8]) -> Result<(), InterfaceError> {
+        self.check_node_id(node_id)?;
+        self.check_access(node_id, index, subindex, true)?;
+        let mut request = [0u8, index as u8, (index >> 8) as u8, subindex, 0, 0, 0, 0];
+        if data.len() <= 4 {
+            request[0] = 0x23 | (((4 - data.len()) as u8) << 2);
+            request[4..4 + data.len()].copy_from_slice(data);
+            let response = self.sdo_request(node_id, index, subindex, request)?;
+            if response[0] != 0x60 {
+                return Err(self.sdo_abort(node_id, index, subindex, 0x0504_0001));
+            }
+        } else {
+            request[0] = 0x21;
+            request[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            let response = self.sdo_request(node_id, index, subindex, request)?;
+            if response[0] != 0x60 {
+                return Err(self.sdo_abort(node_id, index, subindex, 0x0504_0001));
+            }
+            let mut toggle = 0u8;
+            let mut chunks = data.chunks(7).peekable();
+            while let Some(chunk) = chunks.next() {
+                let mut segment = [0u8; 8];
+                segment[0] = toggle | (((7 - chunk.len()) as u8) << 1);
+                if chunks.peek().is_none() {
+                    segment[0] |= 0x01;
+                }
+                segment[1..1 + chunk.len()].copy_from_slice(chunk);
+                let response = self.sdo_request(node_id, index, subindex, segment)?;
+                if response[0] & 0xE0 != 0x20 || response[0] & 0x10 != toggle {
+                    return Err(self.sdo_abort(node_id, index, subindex, 0x0503_0000));
+                }
+                toggle ^= 0x10;
+            }
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_sent(data.len());
+        Ok(())
+    }
+}
+
+impl InterfaceTrait for CanOpenInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.can.poll_fds()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        !self.received.is_empty()
+    }
+
+    // Writes go straight to the CAN interface, so it gets the same write settings
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.base_interface.write_timeout = timeout;
+        self.can.set_write_timeout(timeout);
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.base_interface.nonblocking = nonblocking;
+        self.can.set_nonblocking(nonblocking);
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        self.can.open()?;
+        self.received.clear();
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        self.can.close()
+    }
+
+    // Payload of the next decoded PDO object value, see read_pdo for the full frame
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let data = self.read_pdo()?;
+        if data.data().len() > buffer.len() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..data.data().len()].copy_from_slice(data.data());
+        Ok(data.data().len() as u32)
+    }
+
+    // Raw CAN frame in the layout of CanFrame::to_bytes
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.can.write(buffer)
+    }
+}
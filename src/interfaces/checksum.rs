@@ -0,0 +1,274 @@
+use std::os::fd::RawFd;
+use std::time::Duration;
+
+use super::{BaseInterface, Endianness, InterfaceError, InterfaceErrorKind, InterfaceTrait, ReconnectPolicy};
+
+// CRCs follow the usual catalogue parameters: reflect applies to both the input bytes and
+// the final value, xor_out is applied after reflection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    Crc8 { poly: u8, init: u8, reflect: bool, xor_out: u8 },
+    Crc16 { poly: u16, init: u16, reflect: bool, xor_out: u16 },
+    Crc32 { poly: u32, init: u32, reflect: bool, xor_out: u32 },
+    Fletcher16,
+    // Sums 16 bit words read in the interface byte order, an odd trailing byte is zero padded
+    Fletcher32,
+    // Byte sums, modulo 256 and 65536
+    Sum8,
+    Sum16,
+}
+
+impl Checksum {
+    pub const CRC8: Checksum = Checksum::Crc8 { poly: 0x07, init: 0x00, reflect: false, xor_out: 0x00 };
+    pub const CRC8_MAXIM: Checksum = Checksum::Crc8 { poly: 0x31, init: 0x00, reflect: true, xor_out: 0x00 };
+    pub const CRC16_CCITT_FALSE: Checksum = Checksum::Crc16 { poly: 0x1021, init: 0xFFFF, reflect: false, xor_out: 0x0000 };
+    pub const CRC16_XMODEM: Checksum = Checksum::Crc16 { poly: 0x1021, init: 0x0000, reflect: false, xor_out: 0x0000 };
+    pub const CRC16_MODBUS: Checksum = Checksum::Crc16 { poly: 0x8005, init: 0xFFFF, reflect: true, xor_out: 0x0000 };
+    pub const CRC32: Checksum = Checksum::Crc32 { poly: 0x04C1_1DB7, init: 0xFFFF_FFFF, reflect: true, xor_out: 0xFFFF_FFFF };
+    pub const CRC32C: Checksum = Checksum::Crc32 { poly: 0x1EDC_6F41, init: 0xFFFF_FFFF, reflect: true, xor_out: 0xFFFF_FFFF };
+
+    // Bytes the checksum takes up at the end of a frame
+    pub fn size(&self) -> usize {
+        match self {
+            Checksum::Crc8 { .. } | Checksum::Sum8 => 1,
+            Checksum::Crc16 { .. } | Checksum::Fletcher16 | Checksum::Sum16 => 2,
+            Checksum::Crc32 { .. } | Checksum::Fletcher32 => 4,
+        }
+    }
+
+    pub fn compute(&self, data: &[u8], endianness: Endianness) -> u32 {
+        match *self {
+            Checksum::Crc8 { poly, init, reflect, xor_out } => {
+                crc(data, 8, poly as u32, init as u32, reflect, xor_out as u32)
+            }
+            Checksum::Crc16 { poly, init, reflect, xor_out } => {
+                crc(data, 16, poly as u32, init as u32, reflect, xor_out as u32)
+            }
+            Checksum::Crc32 { poly, init, reflect, xor_out } => crc(data, 32, poly, init, reflect, xor_out),
+            Checksum::Fletcher16 => {
+                let (mut sum1, mut sum2) = (0u32, 0u32);
+                for &byte in data {
+                    sum1 = (sum1 + byte as u32) % 255;
+                    sum2 = (sum2 + sum1) % 255;
+                }
+                (sum2 << 8) | sum1
+            }
+            Checksum::Fletcher32 => {
+                let (mut sum1, mut sum2) = (0u64, 0u64);
+                for word in data.chunks(2) {
+                    let pair = [word[0], word.get(1).copied().unwrap_or(0)];
+                    let word = match endianness {
+                        Endianness::Big => u16::from_be_bytes(pair),
+                        Endianness::Little => u16::from_le_bytes(pair),
+                    };
+                    sum1 = (sum1 + word as u64) % 65535;
+                    sum2 = (sum2 + sum1) % 65535;
+                }
+                ((sum2 << 16) | sum1) as u32
+            }
+            Checksum::Sum8 => data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) as u32,
+            Checksum::Sum16 => data.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16)) as u32,
+        }
+    }
+
+    fn encode_value(self, value: u32, endianness: Endianness) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let mut bytes = bytes[bytes.len() - self.size()..].to_vec();
+        if let Endianness::Little = endianness {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    fn decode_value(self, bytes: &[u8], endianness: Endianness) -> u32 {
+        let fold = |value: u32, byte: &u8| (value << 8) | *byte as u32;
+        match endianness {
+            Endianness::Big => bytes.iter().fold(0, fold),
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+        }
+    }
+}
+
+fn reflect_bits(value: u32, width: u32) -> u32 {
+    value.reverse_bits() >> (32 - width)
+}
+
+// Bit at a time, frames are short enough that a lookup table is not worth keeping per instance
+fn crc(data: &[u8], width: u32, poly: u32, init: u32, reflect: bool, xor_out: u32) -> u32 {
+    let mask = (u64::MAX >> (64 - width)) as u32;
+    let mut crc = init & mask;
+    if reflect {
+        let poly = reflect_bits(poly, width);
+        crc = reflect_bits(crc, width);
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ poly } else { crc >> 1 };
+            }
+        }
+    } else {
+        let top = 1u32 << (width - 1);
+        for &byte in data {
+            crc ^= (byte as u32) << (width - 8);
+            for _ in 0..8 {
+                crc = if crc & top != 0 { (crc << 1) ^ poly } else { crc << 1 };
+            }
+            crc &= mask;
+        }
+    }
+    (crc ^ xor_out) & mask
+}
+
+// Appends a checksum to every frame written and verifies and strips it on every frame read.
+// The wrapped interface has to deliver whole frames, so stream interfaces go through a
+// FramedInterface first.
+pub struct ChecksumInterface<I: InterfaceTrait> {
+    inner: I,
+    checksum: Checksum,
+    endianness: Endianness,
+    drop_bad_frames: bool,
+    frame_count: u64,
+    bad_frame_count: u64,
+}
+
+impl<I: InterfaceTrait> ChecksumInterface<I> {
+    pub fn new(inner: I, checksum: Checksum) -> Self {
+        ChecksumInterface {
+            inner,
+            checksum,
+            endianness: Endianness::Big,
+            drop_bad_frames: false,
+            frame_count: 0,
+            bad_frame_count: 0,
+        }
+    }
+
+    // Byte order of the checksum on the wire, big endian unless set otherwise
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    // Bad frames are skipped silently instead of failing the read with ChecksumError
+    pub fn set_drop_bad_frames(&mut self, drop_bad_frames: bool) {
+        self.drop_bad_frames = drop_bad_frames;
+    }
+
+    pub fn get_checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    // Frames read, good and bad
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_bad_frame_count(&self) -> u64 {
+        self.bad_frame_count
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.frame_count = 0;
+        self.bad_frame_count = 0;
+    }
+
+    pub fn get_inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn get_inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    // Why the frame fails verification, None when it is good
+    fn verify(&self, frame: &[u8]) -> Option<String> {
+        if frame.len() < self.checksum.size() {
+            return Some(format!("{} byte frame is shorter than its checksum", frame.len()));
+        }
+        let (payload, received) = frame.split_at(frame.len() - self.checksum.size());
+        let received = self.checksum.decode_value(received, self.endianness);
+        let expected = self.checksum.compute(payload, self.endianness);
+        if received != expected {
+            return Some(format!("expected 0x{:X}, received 0x{:X}", expected, received));
+        }
+        None
+    }
+}
+
+impl<I: InterfaceTrait> InterfaceTrait for ChecksumInterface<I> {
+    fn base_interface(&self) -> &BaseInterface {
+        self.inner.base_interface()
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        self.inner.base_interface_mut()
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.inner.poll_fds()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.inner.has_pending_input()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout);
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout);
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.inner.set_nonblocking(nonblocking);
+    }
+
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.inner.set_reconnect_policy(policy);
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        self.inner.close()
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let mut frame = vec![0u8; buffer.len() + self.checksum.size()];
+        loop {
+            let bytes_read = self.inner.read(&mut frame)? as usize;
+            if bytes_read == 0 {
+                return Ok(0);
+            }
+            self.frame_count += 1;
+            match self.verify(&frame[..bytes_read]) {
+                None => {
+                    let payload_length = bytes_read - self.checksum.size();
+                    buffer[..payload_length].copy_from_slice(&frame[..payload_length]);
+                    return Ok(payload_length as u32);
+                }
+                Some(reason) => {
+                    self.bad_frame_count += 1;
+                    if !self.drop_bad_frames {
+                        return Err(self.inner.base_interface_mut().raise_with(InterfaceErrorKind::ChecksumError, reason));
+                    }
+                    // Still recorded as the last error, just neither logged nor returned
+                    self.inner.base_interface_mut().raise_quiet(InterfaceErrorKind::ChecksumError);
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        let value = self.checksum.compute(buffer, self.endianness);
+        let mut frame = buffer.to_vec();
+        frame.extend(self.checksum.encode_value(value, self.endianness));
+        self.inner.write(&frame)
+    }
+}
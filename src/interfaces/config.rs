@@ -0,0 +1,676 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::can::CanInterface;
+use super::fifo::FifoInterface;
+use super::message_queue::MessageQueueInterface;
+use super::serial::{SerialConfig, SerialFlowControl, SerialInterface, SerialLine, SerialParity, SerialStopBits};
+use super::shared_memory::SharedMemoryInterface;
+use super::signal::{SignalInterface, UnixSignal};
+use super::tcp::{TcpClientInterface, TcpServerInterface};
+use super::unix_socket::{UnixSocketInterface, UnixSocketKind};
+use super::{Endianness, FileInterface, FramedInterface, Framing, InterfaceManager, InterfaceMode, InterfaceTrait,
+            IsInterfaceManager, PrefixWidth, UDPInterface};
+
+// One problem found in a configuration, `interface` is empty for file level problems
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub interface: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.interface.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "interface {}: {}", self.interface, self.message)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(String),
+    // Every problem found, not just the first one
+    Invalid(Vec<ConfigIssue>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "can not read interface configuration: {}", e),
+            ConfigError::Parse(message) => write!(f, "can not parse interface configuration: {}", message),
+            ConfigError::Invalid(issues) => {
+                write!(f, "invalid interface configuration")?;
+                for issue in issues {
+                    write!(f, "\n  {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeConfig {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EndiannessConfig {
+    Big,
+    Little,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnixSocketKindConfig {
+    Stream,
+    Datagram,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParityConfig {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControlConfig {
+    None,
+    Software,
+    Hardware,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialLineConfig {
+    Generic,
+    Rs232,
+    Rs485,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalConfig {
+    Interrupt,
+    Terminate,
+    Hangup,
+    User1,
+    User2,
+}
+
+fn default_unix_socket_kind() -> UnixSocketKindConfig {
+    UnixSocketKindConfig::Stream
+}
+fn default_endianness() -> EndiannessConfig {
+    EndiannessConfig::Big
+}
+fn default_baud_rate() -> u32 {
+    SerialConfig::default().baud_rate
+}
+fn default_data_bits() -> u8 {
+    SerialConfig::default().data_bits
+}
+fn default_parity() -> ParityConfig {
+    ParityConfig::None
+}
+fn default_stop_bits() -> u8 {
+    1
+}
+fn default_flow_control() -> FlowControlConfig {
+    FlowControlConfig::None
+}
+fn default_serial_line() -> SerialLineConfig {
+    SerialLineConfig::Generic
+}
+// CanInterface reports error frames unless told otherwise
+fn default_error_frames() -> bool {
+    true
+}
+
+// The `type` key of an interface entry and the settings that come with it
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InterfaceKindConfig {
+    File {
+        path: String,
+    },
+    Udp {
+        address: String,
+        port: u16,
+        remote_address: Option<String>,
+        remote_port: Option<u16>,
+        #[serde(default)]
+        broadcast: bool,
+        ttl: Option<u32>,
+        multicast_ttl: Option<u32>,
+        multicast_loop: Option<bool>,
+        multicast_interface: Option<String>,
+        #[serde(default)]
+        multicast_groups: Vec<IpAddr>,
+        #[serde(default)]
+        multicast_sources: Vec<IpAddr>,
+        #[serde(default)]
+        reuse_address: bool,
+        #[serde(default)]
+        reuse_port: bool,
+        receive_buffer_size: Option<usize>,
+        #[serde(default)]
+        source_filter: Vec<IpAddr>,
+        #[serde(default)]
+        reply_to_sender: bool,
+    },
+    TcpClient {
+        address: String,
+        port: u16,
+    },
+    TcpServer {
+        address: String,
+        port: u16,
+        max_clients: Option<usize>,
+    },
+    UnixSocket {
+        path: String,
+        #[serde(default = "default_unix_socket_kind")]
+        socket: UnixSocketKindConfig,
+        #[serde(default)]
+        listen: bool,
+        remote_path: Option<String>,
+    },
+    Fifo {
+        path: String,
+        #[serde(default)]
+        remove_on_close: bool,
+    },
+    MessageQueue {
+        queue: String,
+        max_messages: Option<i64>,
+        max_message_size: Option<i64>,
+        priority: Option<u32>,
+    },
+    SharedMemory {
+        shm_name: String,
+        slot_count: usize,
+        slot_size: usize,
+    },
+    Serial {
+        device: String,
+        #[serde(default = "default_baud_rate")]
+        baud_rate: u32,
+        #[serde(default = "default_data_bits")]
+        data_bits: u8,
+        #[serde(default = "default_parity")]
+        parity: ParityConfig,
+        #[serde(default = "default_stop_bits")]
+        stop_bits: u8,
+        #[serde(default = "default_flow_control")]
+        flow_control: FlowControlConfig,
+        #[serde(default = "default_serial_line")]
+        line: SerialLineConfig,
+    },
+    Can {
+        if_name: String,
+        #[serde(default)]
+        fd_frames: bool,
+        #[serde(default = "default_error_frames")]
+        error_frames: bool,
+    },
+    Signal {
+        signals: Vec<SignalConfig>,
+    },
+}
+
+impl InterfaceKindConfig {
+    fn type_name(&self) -> &'static str {
+        match self {
+            InterfaceKindConfig::File { .. } => "file",
+            InterfaceKindConfig::Udp { .. } => "udp",
+            InterfaceKindConfig::TcpClient { .. } => "tcp_client",
+            InterfaceKindConfig::TcpServer { .. } => "tcp_server",
+            InterfaceKindConfig::UnixSocket { .. } => "unix_socket",
+            InterfaceKindConfig::Fifo { .. } => "fifo",
+            InterfaceKindConfig::MessageQueue { .. } => "message_queue",
+            InterfaceKindConfig::SharedMemory { .. } => "shared_memory",
+            InterfaceKindConfig::Serial { .. } => "serial",
+            InterfaceKindConfig::Can { .. } => "can",
+            InterfaceKindConfig::Signal { .. } => "signal",
+        }
+    }
+
+    // Types whose constructor takes an open mode, the others fix it themselves
+    fn takes_mode(&self) -> bool {
+        matches!(self,
+                 InterfaceKindConfig::File { .. }
+                 | InterfaceKindConfig::UnixSocket { .. }
+                 | InterfaceKindConfig::Fifo { .. }
+                 | InterfaceKindConfig::MessageQueue { .. }
+                 | InterfaceKindConfig::SharedMemory { .. }
+                 | InterfaceKindConfig::Serial { .. })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FramingKindConfig {
+    LengthPrefix {
+        // Header size in bytes: 1, 2 or 4
+        width: u8,
+        #[serde(default = "default_endianness")]
+        endianness: EndiannessConfig,
+    },
+    Delimiter {
+        delimiter: String,
+    },
+    Slip,
+    Cobs,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FramingConfig {
+    #[serde(flatten)]
+    pub kind: FramingKindConfig,
+    pub max_frame_length: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InterfaceConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(flatten)]
+    pub kind: InterfaceKindConfig,
+    pub mode: Option<ModeConfig>,
+    // Marks a log sink, whose own errors are then kept out of the log
+    #[serde(default)]
+    pub log: bool,
+    pub read_timeout_ms: Option<u64>,
+    pub write_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub nonblocking: bool,
+    pub framing: Option<FramingConfig>,
+}
+
+// Interfaces described in TOML or JSON, e.g.
+//
+// [[interfaces]]
+// name = "telemetry"
+// type = "udp"
+// address = "0.0.0.0"
+// port = 5000
+// read_timeout_ms = 500
+// framing = { type = "length_prefix", width = 2 }
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct InterfacesConfig {
+    #[serde(default)]
+    pub interfaces: Vec<InterfaceConfig>,
+}
+
+fn issue(interface: &str, message: impl Into<String>) -> ConfigIssue {
+    ConfigIssue { interface: interface.to_string(), message: message.into() }
+}
+
+// UDPInterface panics on an address it can not parse, so it is caught here instead
+fn check_socket_addr(issues: &mut Vec<ConfigIssue>, name: &str, field: &str, address: &str, port: u16) {
+    if format!("{}:{}", address, port).parse::<SocketAddr>().is_err() {
+        issues.push(issue(name, format!("{} '{}' with port {} is not a socket address", field, address, port)));
+    }
+}
+
+// POSIX IPC names as the message queue and shared memory interfaces accept them: one leading
+// '/' followed by a non-empty name without further slashes
+fn check_ipc_name(issues: &mut Vec<ConfigIssue>, name: &str, field: &str, value: &str) {
+    if !value.starts_with('/') || value.len() < 2 || value[1..].contains('/') {
+        issues.push(issue(name, format!("{} '{}' must be '/' followed by a name without '/'", field, value)));
+    }
+}
+
+impl InterfaceConfig {
+    fn mode(&self) -> InterfaceMode {
+        match self.mode {
+            Some(ModeConfig::Read) => InterfaceMode::Read,
+            Some(ModeConfig::Write) => InterfaceMode::Write,
+            Some(ModeConfig::ReadWrite) | None => InterfaceMode::ReadWrite,
+        }
+    }
+
+    fn check(&self, issues: &mut Vec<ConfigIssue>) {
+        let name = self.name.as_str();
+        if name.is_empty() {
+            issues.push(issue(name, "name must not be empty"));
+        }
+        if self.mode.is_some_and(|mode| mode != ModeConfig::ReadWrite) && !self.kind.takes_mode() {
+            issues.push(issue(name, format!("mode can not be set for {} interfaces", self.kind.type_name())));
+        }
+        match &self.kind {
+            InterfaceKindConfig::File { path } | InterfaceKindConfig::Fifo { path, .. } if path.is_empty() => {
+                issues.push(issue(name, "path must not be empty"));
+            }
+            InterfaceKindConfig::Udp { address, port, remote_address, remote_port, multicast_groups,
+                                       multicast_interface, receive_buffer_size, .. } => {
+                check_socket_addr(issues, name, "address", address, *port);
+                for group in multicast_groups.iter().filter(|group| !group.is_multicast()) {
+                    issues.push(issue(name, format!("multicast group {} is not a multicast address", group)));
+                }
+                if multicast_interface.as_ref().is_some_and(|if_name| if_name.is_empty() || if_name.len() >= libc::IFNAMSIZ) {
+                    issues.push(issue(name, "multicast_interface is not a valid interface name"));
+                }
+                if *receive_buffer_size == Some(0) {
+                    issues.push(issue(name, "receive_buffer_size must be at least 1"));
+                }
+                match (remote_address, remote_port) {
+                    (Some(remote_address), Some(remote_port)) => {
+                        check_socket_addr(issues, name, "remote_address", remote_address, *remote_port);
+                    }
+                    (None, None) => {}
+                    _ => issues.push(issue(name, "remote_address and remote_port go together")),
+                }
+            }
+            InterfaceKindConfig::TcpClient { address, port } => {
+                if address.is_empty() {
+                    issues.push(issue(name, "address must not be empty"));
+                }
+                if *port == 0 {
+                    issues.push(issue(name, "port must not be 0"));
+                }
+            }
+            InterfaceKindConfig::TcpServer { address, port, max_clients } => {
+                check_socket_addr(issues, name, "address", address, *port);
+                if *max_clients == Some(0) {
+                    issues.push(issue(name, "max_clients must be at least 1"));
+                }
+            }
+            InterfaceKindConfig::UnixSocket { path, socket, listen, remote_path } => {
+                if path.is_empty() {
+                    issues.push(issue(name, "path must not be empty"));
+                }
+                if *listen && *socket == UnixSocketKindConfig::Datagram {
+                    issues.push(issue(name, "listen only applies to stream sockets"));
+                }
+                if remote_path.is_some() && *socket == UnixSocketKindConfig::Stream {
+                    issues.push(issue(name, "remote_path only applies to datagram sockets"));
+                }
+            }
+            InterfaceKindConfig::MessageQueue { queue, max_messages, max_message_size, .. } => {
+                check_ipc_name(issues, name, "queue", queue);
+                if max_messages.is_some() != max_message_size.is_some() {
+                    issues.push(issue(name, "max_messages and max_message_size go together"));
+                }
+                if max_messages.is_some_and(|max_messages| max_messages <= 0)
+                    || max_message_size.is_some_and(|max_message_size| max_message_size <= 0) {
+                    issues.push(issue(name, "queue attributes must be positive"));
+                }
+            }
+            InterfaceKindConfig::SharedMemory { shm_name, slot_count, slot_size } => {
+                check_ipc_name(issues, name, "shm_name", shm_name);
+                if *slot_count == 0 || *slot_size == 0 {
+                    issues.push(issue(name, "slot_count and slot_size must be at least 1"));
+                }
+            }
+            InterfaceKindConfig::Serial { device, baud_rate, data_bits, stop_bits, .. } => {
+                if device.is_empty() {
+                    issues.push(issue(name, "device must not be empty"));
+                }
+                if *baud_rate == 0 {
+                    issues.push(issue(name, "baud_rate must not be 0"));
+                }
+                if !(5..=8).contains(data_bits) {
+                    issues.push(issue(name, format!("data_bits {} is not between 5 and 8", data_bits)));
+                }
+                if *stop_bits != 1 && *stop_bits != 2 {
+                    issues.push(issue(name, format!("stop_bits {} is neither 1 nor 2", stop_bits)));
+                }
+            }
+            InterfaceKindConfig::Can { if_name, .. } if if_name.is_empty() || if_name.len() >= libc::IFNAMSIZ => {
+                issues.push(issue(name, format!("if_name '{}' is not a valid interface name", if_name)));
+            }
+            InterfaceKindConfig::Signal { signals } if signals.is_empty() => {
+                issues.push(issue(name, "signals must not be empty"));
+            }
+            _ => {}
+        }
+        if let Some(framing) = &self.framing {
+            match &framing.kind {
+                FramingKindConfig::LengthPrefix { width, .. } if ![1, 2, 4].contains(width) => {
+                    issues.push(issue(name, format!("length prefix width {} is not 1, 2 or 4", width)));
+                }
+                FramingKindConfig::Delimiter { delimiter } if delimiter.is_empty() => {
+                    issues.push(issue(name, "framing delimiter must not be empty"));
+                }
+                _ => {}
+            }
+            if framing.max_frame_length == Some(0) {
+                issues.push(issue(name, "max_frame_length must be at least 1"));
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut issues = Vec::new();
+        self.check(&mut issues);
+        if issues.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(issues)) }
+    }
+
+    // Builds the interface closed, with framing applied on top when configured
+    pub fn build(&self) -> Result<Box<dyn InterfaceTrait>, ConfigError> {
+        self.validate()?;
+        let name = self.name.clone();
+        let description = self.description.clone();
+        let log_if = Some(self.log);
+        let mode = self.mode();
+        let mut interface: Box<dyn InterfaceTrait> = match &self.kind {
+            InterfaceKindConfig::File { path } => {
+                Box::new(FileInterface::new(name, description, path.clone(), mode, log_if))
+            }
+            InterfaceKindConfig::Udp { address, port, remote_address, remote_port, broadcast, ttl, multicast_ttl,
+                                       multicast_loop, multicast_interface, multicast_groups, multicast_sources,
+                                       reuse_address, reuse_port, receive_buffer_size, source_filter,
+                                       reply_to_sender } => {
+                let mut udp = UDPInterface::new(name.clone(), description, address.clone(), *port, log_if);
+                if let (Some(remote_address), Some(remote_port)) = (remote_address, remote_port) {
+                    udp.append_remote_addr(remote_address.clone(), *remote_port);
+                }
+                // Nothing is applied to a socket yet, so these only record the settings
+                let recorded = udp.set_broadcast(*broadcast)
+                    .and(udp.set_ttl(*ttl))
+                    .and(udp.set_multicast_ttl(*multicast_ttl))
+                    .and(udp.set_multicast_loop(multicast_loop.unwrap_or(true)))
+                    .and(udp.set_receive_buffer_size(*receive_buffer_size))
+                    .and(multicast_groups.iter().try_for_each(|group| udp.join_multicast_group(*group)));
+                if recorded.is_err() {
+                    return Err(ConfigError::Invalid(vec![issue(&name, "UDP socket options were rejected")]));
+                }
+                udp.set_multicast_interface(multicast_interface.clone());
+                udp.set_multicast_sources(multicast_sources.clone());
+                udp.set_reuse_address(*reuse_address);
+                udp.set_reuse_port(*reuse_port);
+                udp.set_source_filter(source_filter.clone());
+                udp.set_reply_to_sender(*reply_to_sender);
+                Box::new(udp)
+            }
+            InterfaceKindConfig::TcpClient { address, port } => {
+                Box::new(TcpClientInterface::new(name, description, address.clone(), *port, log_if))
+            }
+            InterfaceKindConfig::TcpServer { address, port, max_clients } => {
+                let mut server = TcpServerInterface::new(name, description, address.clone(), *port, log_if);
+                server.set_max_clients(*max_clients);
+                Box::new(server)
+            }
+            InterfaceKindConfig::UnixSocket { path, socket, listen, remote_path } => {
+                let kind = match socket {
+                    UnixSocketKindConfig::Stream => UnixSocketKind::Stream,
+                    UnixSocketKindConfig::Datagram => UnixSocketKind::Datagram,
+                };
+                let mut unix_socket = UnixSocketInterface::new(name, description, path.clone(), kind, mode, log_if);
+                unix_socket.set_listen(*listen);
+                if let Some(remote_path) = remote_path {
+                    unix_socket.append_remote_path(remote_path.clone());
+                }
+                Box::new(unix_socket)
+            }
+            InterfaceKindConfig::Fifo { path, remove_on_close } => {
+                let mut fifo = FifoInterface::new(name, description, path.clone(), mode, log_if);
+                fifo.set_remove_on_close(*remove_on_close);
+                Box::new(fifo)
+            }
+            InterfaceKindConfig::MessageQueue { queue, max_messages, max_message_size, priority } => {
+                let mut message_queue = MessageQueueInterface::new(name, description, queue.clone(), mode, log_if);
+                if let (Some(max_messages), Some(max_message_size)) = (max_messages, max_message_size) {
+                    message_queue.set_queue_attributes(*max_messages, *max_message_size);
+                }
+                if let Some(priority) = priority {
+                    message_queue.set_send_priority(*priority);
+                }
+                Box::new(message_queue)
+            }
+            InterfaceKindConfig::SharedMemory { shm_name, slot_count, slot_size } => {
+                Box::new(SharedMemoryInterface::new(name, description, shm_name.clone(), *slot_count, *slot_size, mode, log_if))
+            }
+            InterfaceKindConfig::Serial { device, baud_rate, data_bits, parity, stop_bits, flow_control, line } => {
+                let config = SerialConfig {
+                    baud_rate: *baud_rate,
+                    data_bits: *data_bits,
+                    parity: match parity {
+                        ParityConfig::None => SerialParity::None,
+                        ParityConfig::Odd => SerialParity::Odd,
+                        ParityConfig::Even => SerialParity::Even,
+                    },
+                    stop_bits: if *stop_bits == 2 { SerialStopBits::Two } else { SerialStopBits::One },
+                    flow_control: match flow_control {
+                        FlowControlConfig::None => SerialFlowControl::None,
+                        FlowControlConfig::Software => SerialFlowControl::Software,
+                        FlowControlConfig::Hardware => SerialFlowControl::Hardware,
+                    },
+                    line: match line {
+                        SerialLineConfig::Generic => SerialLine::Generic,
+                        SerialLineConfig::Rs232 => SerialLine::RS232,
+                        SerialLineConfig::Rs485 => SerialLine::RS485,
+                    },
+                };
+                Box::new(SerialInterface::new(name, description, device.clone(), config, mode, log_if))
+            }
+            InterfaceKindConfig::Can { if_name, fd_frames, error_frames } => {
+                let mut can = CanInterface::new(name.clone(), description, if_name.clone(), log_if);
+                // Only recorded while closed, applied once the socket is opened
+                if can.set_fd_frames(*fd_frames).and(can.set_error_frames(*error_frames)).is_err() {
+                    return Err(ConfigError::Invalid(vec![issue(&name, "CAN socket options were rejected")]));
+                }
+                Box::new(can)
+            }
+            InterfaceKindConfig::Signal { signals } => {
+                let signals = signals
+                    .iter()
+                    .map(|signal| match signal {
+                        SignalConfig::Interrupt => UnixSignal::Interrupt,
+                        SignalConfig::Terminate => UnixSignal::Terminate,
+                        SignalConfig::Hangup => UnixSignal::Hangup,
+                        SignalConfig::User1 => UnixSignal::User1,
+                        SignalConfig::User2 => UnixSignal::User2,
+                    })
+                    .collect();
+                Box::new(SignalInterface::new(name, description, signals, log_if))
+            }
+        };
+        if let Some(read_timeout_ms) = self.read_timeout_ms {
+            interface.set_read_timeout(Some(Duration::from_millis(read_timeout_ms)));
+        }
+        if let Some(write_timeout_ms) = self.write_timeout_ms {
+            interface.set_write_timeout(Some(Duration::from_millis(write_timeout_ms)));
+        }
+        interface.set_nonblocking(self.nonblocking);
+        if let Some(framing) = &self.framing {
+            let endianness = |endianness: &EndiannessConfig| match endianness {
+                EndiannessConfig::Big => Endianness::Big,
+                EndiannessConfig::Little => Endianness::Little,
+            };
+            let kind = match &framing.kind {
+                FramingKindConfig::LengthPrefix { width, endianness: byte_order } => Framing::LengthPrefix {
+                    width: match width {
+                        1 => PrefixWidth::U8,
+                        2 => PrefixWidth::U16,
+                        _ => PrefixWidth::U32,
+                    },
+                    endianness: endianness(byte_order),
+                },
+                FramingKindConfig::Delimiter { delimiter } => Framing::Delimiter(delimiter.as_bytes().to_vec()),
+                FramingKindConfig::Slip => Framing::Slip,
+                FramingKindConfig::Cobs => Framing::Cobs,
+            };
+            let mut framed = FramedInterface::new(interface, kind)
+                .map_err(|e| ConfigError::Invalid(vec![issue(&self.name, e.to_string())]))?;
+            if let Some(max_frame_length) = framing.max_frame_length {
+                framed.set_max_frame_length(max_frame_length);
+            }
+            interface = Box::new(framed);
+        }
+        Ok(interface)
+    }
+}
+
+impl InterfacesConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, ConfigError> {
+        serde_json::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    // The format follows the extension: .json is JSON, anything else is read as TOML
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path.as_ref()).map_err(ConfigError::Io)?;
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut issues = Vec::new();
+        let mut names = HashSet::new();
+        for interface in &self.interfaces {
+            if !names.insert(interface.name.as_str()) {
+                issues.push(issue(&interface.name, "name is used more than once"));
+            }
+            interface.check(&mut issues);
+        }
+        if issues.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(issues)) }
+    }
+
+    // Interfaces are added in file order and left closed
+    pub fn build(&self) -> Result<InterfaceManager, ConfigError> {
+        self.validate()?;
+        let mut manager = InterfaceManager::new();
+        for interface in &self.interfaces {
+            manager.add_interface(interface.build()?)
+                .map_err(|e| ConfigError::Invalid(vec![issue(&interface.name, e.to_string())]))?;
+        }
+        Ok(manager)
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::SystemTime;
+
+use super::{InterfaceErrorKind, InterfaceEvent};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+// What subscribers get for every event: the event, the interface it happened on and when
+#[derive(Clone, Debug)]
+pub struct InterfaceEventRecord {
+    pub interface: String,
+    pub event: InterfaceEvent,
+    // Set for ErrorOccurred, the kind of error that was raised
+    pub error: Option<InterfaceErrorKind>,
+    pub timestamp: SystemTime,
+}
+
+enum Subscriber {
+    Callback(Box<dyn FnMut(&InterfaceEventRecord) + Send>),
+    Channel(Sender<InterfaceEventRecord>),
+}
+
+pub(super) struct EventSubscribers {
+    subscribers: Vec<(SubscriptionId, Subscriber)>,
+    next_id: u64,
+}
+
+impl EventSubscribers {
+    pub(super) fn new() -> Self {
+        EventSubscribers {
+            subscribers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn add(&mut self, subscriber: Subscriber) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, subscriber));
+        id
+    }
+
+    pub(super) fn add_callback(&mut self, callback: Box<dyn FnMut(&InterfaceEventRecord) + Send>) -> SubscriptionId {
+        self.add(Subscriber::Callback(callback))
+    }
+
+    pub(super) fn add_sender(&mut self, sender: Sender<InterfaceEventRecord>) -> SubscriptionId {
+        self.add(Subscriber::Channel(sender))
+    }
+
+    pub(super) fn add_channel(&mut self) -> (SubscriptionId, Receiver<InterfaceEventRecord>) {
+        let (sender, receiver) = mpsc::channel();
+        (self.add_sender(sender), receiver)
+    }
+
+    pub(super) fn remove(&mut self, id: SubscriptionId) -> bool {
+        let count = self.subscribers.len();
+        self.subscribers.retain(|(subscriber_id, _)| *subscriber_id != id);
+        self.subscribers.len() != count
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    // Channels whose receiver is gone unsubscribe themselves here
+    pub(super) fn notify(&mut self, record: &InterfaceEventRecord) {
+        self.subscribers.retain_mut(|(_, subscriber)| match subscriber {
+            Subscriber::Callback(callback) => {
+                callback(record);
+                true
+            }
+            Subscriber::Channel(sender) => sender.send(record.clone()).is_ok(),
+        });
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+use std::os::fd::RawFd;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceTrait, ReconnectPolicy};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FaultKind {
+    Loss,
+    BitFlip,
+    Truncation,
+    Duplication,
+    Reordering,
+    Latency,
+    Disconnect,
+}
+
+#[derive(Clone, Debug)]
+pub struct FaultPolicy {
+    // Chance of each fault per message, 0.0 (never) to 1.0 (always)
+    pub loss: f64,
+    // Flips one bit of the message
+    pub bit_flip: f64,
+    // Cuts the message short, keeping at least one byte
+    pub truncation: f64,
+    pub duplication: f64,
+    // Holds the message back and delivers it after the next one
+    pub reordering: f64,
+    // Every message is delayed by latency plus a random part of jitter
+    pub latency: Duration,
+    pub jitter: Duration,
+    // The connection drops after being open this long and can not be opened again for
+    // disconnect_duration; None never drops it
+    pub disconnect_interval: Option<Duration>,
+    pub disconnect_duration: Duration,
+    // Directions the message faults apply to
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+impl Default for FaultPolicy {
+    fn default() -> Self {
+        FaultPolicy {
+            loss: 0.0,
+            bit_flip: 0.0,
+            truncation: 0.0,
+            duplication: 0.0,
+            reordering: 0.0,
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            disconnect_interval: None,
+            disconnect_duration: Duration::from_secs(1),
+            on_read: true,
+            on_write: true,
+        }
+    }
+}
+
+// Injects the faults of a FaultPolicy into the traffic of the wrapped interface, each one
+// counted in its stats (see InterfaceStats::get_fault_count). The same seed and policy give
+// the same faults for the same sequence of messages.
+pub struct FaultInterface<I: InterfaceTrait> {
+    inner: I,
+    policy: FaultPolicy,
+    random_state: u64,
+    // Read messages waiting to be handed out: duplicates and messages that were held back
+    rx_queue: VecDeque<Vec<u8>>,
+    rx_held: Option<Vec<u8>>,
+    tx_held: Option<Vec<u8>>,
+    next_disconnect: Option<Instant>,
+    reopen_after: Option<Instant>,
+}
+
+impl<I: InterfaceTrait> FaultInterface<I> {
+    pub fn new(inner: I, policy: FaultPolicy, seed: u64) -> Self {
+        FaultInterface {
+            inner,
+            policy,
+            random_state: seed,
+            rx_queue: VecDeque::new(),
+            rx_held: None,
+            tx_held: None,
+            next_disconnect: None,
+            reopen_after: None,
+        }
+    }
+
+    // A new disconnect interval counts from the next open
+    pub fn set_policy(&mut self, policy: FaultPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn get_policy(&self) -> &FaultPolicy {
+        &self.policy
+    }
+
+    pub fn get_inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn get_inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    // splitmix64, works from any seed including 0
+    fn next_random(&mut self) -> u64 {
+        self.random_state = self.random_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.random_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn random(&mut self) -> f64 {
+        (self.next_random() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, limit: usize) -> usize {
+        (self.next_random() % limit as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.random() < probability
+    }
+
+    fn record(&mut self, kind: FaultKind) {
+        self.inner.base_interface_mut().stats.record_fault(kind);
+    }
+
+    fn corrupt(&mut self, data: &mut Vec<u8>) {
+        if data.len() > 1 && self.chance(self.policy.truncation) {
+            let length = 1 + self.below(data.len() - 1);
+            data.truncate(length);
+            self.record(FaultKind::Truncation);
+        }
+        if !data.is_empty() && self.chance(self.policy.bit_flip) {
+            let bit = self.below(data.len() * 8);
+            data[bit / 8] ^= 1 << (bit % 8);
+            self.record(FaultKind::BitFlip);
+        }
+    }
+
+    fn delay(&mut self) {
+        let mut delay = self.policy.latency;
+        if !self.policy.jitter.is_zero() {
+            delay += self.policy.jitter.mul_f64(self.random());
+        }
+        if !delay.is_zero() {
+            self.record(FaultKind::Latency);
+            thread::sleep(delay);
+        }
+    }
+
+    // Closes the wrapped interface once the disconnect interval ran out, anything held
+    // back goes with the connection
+    fn check_connection(&mut self) -> Result<(), InterfaceError> {
+        match self.next_disconnect {
+            Some(at) if Instant::now() >= at => {}
+            _ => return Ok(()),
+        }
+        self.next_disconnect = None;
+        self.reopen_after = Some(Instant::now() + self.policy.disconnect_duration);
+        self.rx_queue.clear();
+        self.rx_held = None;
+        self.tx_held = None;
+        let _ = self.inner.close();
+        self.record(FaultKind::Disconnect);
+        let base = self.inner.base_interface_mut();
+        base.set_event(InterfaceEvent::ConnectionLost);
+        Err(base.raise(InterfaceErrorKind::ConnectionLost))
+    }
+
+    fn deliver(&mut self, data: Vec<u8>, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if data.len() > buffer.len() {
+            self.rx_queue.push_front(data);
+            return Err(self.inner.base_interface_mut().raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..data.len()].copy_from_slice(&data);
+        Ok(data.len() as u32)
+    }
+}
+
+impl<I: InterfaceTrait> InterfaceTrait for FaultInterface<I> {
+    fn base_interface(&self) -> &BaseInterface {
+        self.inner.base_interface()
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        self.inner.base_interface_mut()
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.inner.poll_fds()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        !self.rx_queue.is_empty() || self.inner.has_pending_input()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout);
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout);
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.inner.set_nonblocking(nonblocking);
+    }
+
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.inner.set_reconnect_policy(policy);
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if self.reopen_after.is_some_and(|after| Instant::now() < after) {
+            return Err(self.inner.base_interface_mut().raise(InterfaceErrorKind::ConnectionLost));
+        }
+        self.inner.open()?;
+        self.reopen_after = None;
+        self.next_disconnect = self.policy.disconnect_interval.map(|interval| Instant::now() + interval);
+        Ok(())
+    }
+
+    // A write still held back for reordering is sent before closing
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        let flushed = match self.tx_held.take() {
+            Some(held) => self.inner.write(&held),
+            None => Ok(()),
+        };
+        self.rx_queue.clear();
+        self.rx_held = None;
+        self.next_disconnect = None;
+        self.inner.close()?;
+        flushed
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        self.check_connection()?;
+        if let Some(data) = self.rx_queue.pop_front() {
+            return self.deliver(data, buffer);
+        }
+        if !self.policy.on_read {
+            return self.inner.read(buffer);
+        }
+        loop {
+            let bytes_read = match self.inner.read(buffer) {
+                Ok(bytes_read) => bytes_read as usize,
+                // Nothing came along to overtake the held back message, it goes out late instead
+                Err(e) if e.kind() == InterfaceErrorKind::Timeout && self.rx_held.is_some() => {
+                    let held = self.rx_held.take().unwrap();
+                    return self.deliver(held, buffer);
+                }
+                Err(e) => return Err(e),
+            };
+            // The held back message still goes out ahead of the end of file
+            if bytes_read == 0 {
+                return match self.rx_held.take() {
+                    Some(held) => self.deliver(held, buffer),
+                    None => Ok(0),
+                };
+            }
+            if self.chance(self.policy.loss) {
+                self.record(FaultKind::Loss);
+                continue;
+            }
+            let mut data = buffer[..bytes_read].to_vec();
+            self.corrupt(&mut data);
+            if self.rx_held.is_none() && self.chance(self.policy.reordering) {
+                self.record(FaultKind::Reordering);
+                self.rx_held = Some(data);
+                continue;
+            }
+            if self.chance(self.policy.duplication) {
+                self.record(FaultKind::Duplication);
+                self.rx_queue.push_back(data.clone());
+            }
+            if let Some(held) = self.rx_held.take() {
+                self.rx_queue.push_back(held);
+            }
+            self.delay();
+            return self.deliver(data, buffer);
+        }
+    }
+
+    // A lost write succeeds without reaching the wrapped interface
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        self.check_connection()?;
+        if !self.policy.on_write {
+            return self.inner.write(buffer);
+        }
+        if self.chance(self.policy.loss) {
+            self.record(FaultKind::Loss);
+            return Ok(());
+        }
+        let mut data = buffer.to_vec();
+        self.corrupt(&mut data);
+        self.delay();
+        if self.tx_held.is_none() && self.chance(self.policy.reordering) {
+            self.record(FaultKind::Reordering);
+            self.tx_held = Some(data);
+            return Ok(());
+        }
+        let duplicate = self.chance(self.policy.duplication);
+        self.inner.write(&data)?;
+        if duplicate {
+            self.record(FaultKind::Duplication);
+            self.inner.write(&data)?;
+        }
+        if let Some(held) = self.tx_held.take() {
+            self.inner.write(&held)?;
+        }
+        Ok(())
+    }
+}
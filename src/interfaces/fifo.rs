@@ -0,0 +1,165 @@
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::FileTypeExt;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol,
+            InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+pub struct FifoInterface {
+    fifo_path: String,
+    file: Option<File>,
+    created: bool,
+    remove_on_close: bool,
+    base_interface: BaseInterface,
+}
+
+impl FifoInterface {
+    pub fn new(name: String, description: String, fifo_path: String, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        FifoInterface {
+            fifo_path,
+            file: None,
+            created: false,
+            remove_on_close: false,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::Pipe},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    // Only a FIFO created by this interface is ever removed
+    pub fn set_remove_on_close(&mut self, remove_on_close: bool) {
+        self.remove_on_close = remove_on_close;
+    }
+
+    fn create_fifo(&mut self) -> Result<(), InterfaceError> {
+        match fs::metadata(&self.fifo_path) {
+            Ok(metadata) => {
+                if !metadata.file_type().is_fifo() {
+                    return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                                                               format!("{} exists and is not a FIFO",
+                                                                       self.fifo_path)));
+                }
+                Ok(())
+            }
+            Err(_) => {
+                let path = CString::new(self.fifo_path.as_str())
+                    .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+                if unsafe { libc::mkfifo(path.as_ptr(), 0o660) } != 0 {
+                    return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+                }
+                self.created = true;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl InterfaceTrait for FifoInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.file.iter().map(|file| file.as_raw_fd()).collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        self.create_fifo()?;
+        // Opening one end blocks until the other end shows up, except in ReadWrite mode
+        match self.base_interface.get_mode() {
+            InterfaceMode::Read => {
+                self.file = Some(File::open(&self.fifo_path).map_err(|e| self.base_interface.raise_io(e))?);
+            }
+            InterfaceMode::Write => {
+                self.file = Some(OpenOptions::new()
+                    .write(true)
+                    .open(&self.fifo_path)
+                    .map_err(|e| self.base_interface.raise_io(e))?);
+            }
+            InterfaceMode::ReadWrite => {
+                self.file = Some(OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&self.fifo_path)
+                    .map_err(|e| self.base_interface.raise_io(e))?);
+            }
+        }
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        if self.file.take().is_some() {
+            self.base_interface.status = InterfaceStatus::Disconnected;
+            if self.created && self.remove_on_close {
+                self.created = false;
+                fs::remove_file(&self.fifo_path).map_err(|e| self.base_interface.raise_io(e))?;
+            }
+            Ok(())
+        } else {
+            Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        match self.base_interface.get_status() {
+            InterfaceStatus::Connected => {
+                self.base_interface.error = None;
+                if let Some(file) = self.file.as_mut() {
+                    self.base_interface.wait_ready(file.as_raw_fd(), false)?;
+                    let bytes_read = file.read(buffer).map_err(|e| self.base_interface.raise_io(e))?;
+                    // 0 bytes means every writer closed its end, there is nothing to count
+                    if bytes_read > 0 {
+                        self.base_interface.data_received(bytes_read);
+                    }
+                    Ok(bytes_read as u32)
+                } else {
+                    Err(self.base_interface.raise(InterfaceErrorKind::GenericError))
+                }
+            }
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        match self.base_interface.get_status() {
+            InterfaceStatus::Connected => {
+                self.base_interface.error = None;
+                if let Some(file) = self.file.as_mut() {
+                    self.base_interface.wait_ready(file.as_raw_fd(), true)?;
+                    file.write_all(buffer).map_err(|e| self.base_interface.raise_io(e))?;
+                    self.base_interface.data_sent(buffer.len());
+                    Ok(())
+                } else {
+                    Err(self.base_interface.raise(InterfaceErrorKind::GenericError))
+                }
+            }
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+}
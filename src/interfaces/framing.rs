@@ -0,0 +1,416 @@
+use std::os::fd::RawFd;
+use std::time::Duration;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceTrait, ReconnectPolicy};
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+const COBS_DELIMITER: u8 = 0x00;
+const READ_CHUNK: usize = 4096;
+const DEFAULT_MAX_FRAME_LENGTH: usize = 65536;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefixWidth {
+    U8,
+    U16,
+    U32,
+}
+
+impl PrefixWidth {
+    fn bytes(&self) -> usize {
+        match self {
+            PrefixWidth::U8 => 1,
+            PrefixWidth::U16 => 2,
+            PrefixWidth::U32 => 4,
+        }
+    }
+    fn max_length(&self) -> usize {
+        match self {
+            PrefixWidth::U8 => u8::MAX as usize,
+            PrefixWidth::U16 => u16::MAX as usize,
+            PrefixWidth::U32 => u32::MAX as usize,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Framing {
+    // Payload preceded by its length, the header itself is not counted
+    LengthPrefix { width: PrefixWidth, endianness: Endianness },
+    // Payload followed by the delimiter, which must not appear inside it
+    Delimiter(Vec<u8>),
+    // RFC 1055
+    Slip,
+    // Consistent Overhead Byte Stuffing, frames terminated by a zero byte
+    Cobs,
+}
+
+// Turns the byte stream of the wrapped interface into whole messages: every read returns
+// exactly one frame and every write sends one. A corrupt frame fails that read with
+// FramingError and the next read carries on from the following frame.
+pub struct FramedInterface<I: InterfaceTrait> {
+    inner: I,
+    framing: Framing,
+    max_frame_length: usize,
+    rx_buffer: Vec<u8>,
+    // A decoded frame that did not fit the caller's buffer, handed out by the next read
+    rx_frame: Option<Vec<u8>>,
+    // Set once an oversized frame was dropped, input is discarded up to the next delimiter
+    resync: bool,
+}
+
+impl<I: InterfaceTrait> FramedInterface<I> {
+    // An empty delimiter would match everywhere, it fails with GenericError
+    pub fn new(mut inner: I, framing: Framing) -> Result<Self, InterfaceError> {
+        if let Framing::Delimiter(delimiter) = &framing
+            && delimiter.is_empty() {
+            return Err(inner.base_interface_mut().raise_with(InterfaceErrorKind::GenericError,
+                                                             "framing delimiter must not be empty"));
+        }
+        Ok(FramedInterface {
+            inner,
+            framing,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            rx_buffer: Vec::new(),
+            rx_frame: None,
+            resync: false,
+        })
+    }
+
+    // Longest payload accepted in either direction, longer frames are treated as corrupt
+    pub fn set_max_frame_length(&mut self, max_frame_length: usize) {
+        self.max_frame_length = max_frame_length;
+    }
+
+    pub fn get_framing(&self) -> &Framing {
+        &self.framing
+    }
+
+    pub fn get_inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn get_inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    fn delimiter(&self) -> &[u8] {
+        match &self.framing {
+            Framing::LengthPrefix { .. } => &[],
+            Framing::Delimiter(delimiter) => delimiter,
+            Framing::Slip => &[SLIP_END],
+            Framing::Cobs => &[COBS_DELIMITER],
+        }
+    }
+
+    // Most bytes the encoded form of a max_frame_length payload can take up on the wire
+    fn max_encoded_length(&self) -> usize {
+        match &self.framing {
+            Framing::LengthPrefix { .. } | Framing::Delimiter(_) => self.max_frame_length,
+            Framing::Slip => self.max_frame_length * 2,
+            Framing::Cobs => self.max_frame_length + self.max_frame_length / 254 + 1,
+        }
+    }
+
+    fn frame_ready(&self) -> bool {
+        if self.rx_frame.is_some() {
+            return true;
+        }
+        match &self.framing {
+            Framing::LengthPrefix { width, endianness } => {
+                if self.rx_buffer.len() < width.bytes() {
+                    return false;
+                }
+                let length = decode_length(&self.rx_buffer[..width.bytes()], *endianness);
+                length > self.max_frame_length || self.rx_buffer.len() >= width.bytes() + length
+            }
+            _ => find(&self.rx_buffer, self.delimiter()).is_some(),
+        }
+    }
+
+    // Next complete frame in rx_buffer, or why the frame at its head had to be dropped
+    fn extract_frame(&mut self) -> Option<Result<Vec<u8>, String>> {
+        if let Framing::LengthPrefix { width, endianness } = self.framing {
+            let header = width.bytes();
+            if self.rx_buffer.len() < header {
+                return None;
+            }
+            let length = decode_length(&self.rx_buffer[..header], endianness);
+            if length > self.max_frame_length {
+                // Most likely a corrupt header, slide forward a byte at a time until a sane one
+                self.rx_buffer.drain(..1);
+                return Some(Err(format!("length prefix {} exceeds the {} byte limit", length, self.max_frame_length)));
+            }
+            if self.rx_buffer.len() < header + length {
+                return None;
+            }
+            let frame = self.rx_buffer[header..header + length].to_vec();
+            self.rx_buffer.drain(..header + length);
+            return Some(Ok(frame));
+        }
+        loop {
+            let delimiter_length = self.delimiter().len();
+            let position = match find(&self.rx_buffer, self.delimiter()) {
+                Some(position) => position,
+                None => {
+                    if self.rx_buffer.len() <= self.max_encoded_length() {
+                        return None;
+                    }
+                    // Keep what could be the start of a delimiter split across two reads
+                    let dropped = self.rx_buffer.len() + 1 - delimiter_length;
+                    self.rx_buffer.drain(..dropped);
+                    if self.resync {
+                        return None;
+                    }
+                    self.resync = true;
+                    return Some(Err(format!("no delimiter within {} bytes", self.max_encoded_length())));
+                }
+            };
+            let raw: Vec<u8> = self.rx_buffer.drain(..position + delimiter_length).take(position).collect();
+            if self.resync {
+                self.resync = false;
+                continue;
+            }
+            // Back to back delimiters carry nothing, SLIP senders even start frames with one
+            if raw.is_empty() {
+                continue;
+            }
+            return Some(match self.framing {
+                Framing::Slip => slip_decode(&raw),
+                Framing::Cobs => cobs_decode(&raw),
+                _ => Ok(raw),
+            });
+        }
+    }
+
+    fn encode(&mut self, payload: &[u8]) -> Result<Vec<u8>, InterfaceError> {
+        if payload.len() > self.max_frame_length {
+            return Err(self.inner.base_interface_mut().raise_with(
+                InterfaceErrorKind::Overflow,
+                format!("{} byte frame exceeds the {} byte limit", payload.len(), self.max_frame_length),
+            ));
+        }
+        match &self.framing {
+            Framing::LengthPrefix { width, endianness } => {
+                if payload.len() > width.max_length() {
+                    return Err(self.inner.base_interface_mut().raise_with(
+                        InterfaceErrorKind::Overflow,
+                        format!("{} byte frame does not fit a {} byte length prefix", payload.len(), width.bytes()),
+                    ));
+                }
+                let mut encoded = encode_length(payload.len(), *width, *endianness);
+                encoded.extend_from_slice(payload);
+                Ok(encoded)
+            }
+            Framing::Delimiter(delimiter) => {
+                if find(payload, delimiter).is_some() {
+                    return Err(self.inner.base_interface_mut().raise_with(
+                        InterfaceErrorKind::FramingError,
+                        "frame contains the delimiter",
+                    ));
+                }
+                let mut encoded = payload.to_vec();
+                encoded.extend_from_slice(delimiter);
+                Ok(encoded)
+            }
+            Framing::Slip => Ok(slip_encode(payload)),
+            Framing::Cobs => {
+                let mut encoded = cobs_encode(payload);
+                encoded.push(COBS_DELIMITER);
+                Ok(encoded)
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn decode_length(header: &[u8], endianness: Endianness) -> usize {
+    let fold = |length: usize, byte: &u8| (length << 8) | *byte as usize;
+    match endianness {
+        Endianness::Big => header.iter().fold(0, fold),
+        Endianness::Little => header.iter().rev().fold(0, fold),
+    }
+}
+
+fn encode_length(length: usize, width: PrefixWidth, endianness: Endianness) -> Vec<u8> {
+    let bytes = (length as u32).to_be_bytes();
+    let mut header = bytes[bytes.len() - width.bytes()..].to_vec();
+    if let Endianness::Little = endianness {
+        header.reverse();
+    }
+    header
+}
+
+fn slip_encode(payload: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(payload.len() + 2);
+    // Leading END flushes any line noise the receiver collected since the last frame
+    encoded.push(SLIP_END);
+    for &byte in payload {
+        match byte {
+            SLIP_END => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            _ => encoded.push(byte),
+        }
+    }
+    encoded.push(SLIP_END);
+    encoded
+}
+
+fn slip_decode(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut frame = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != SLIP_ESC {
+            frame.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(&SLIP_ESC_END) => frame.push(SLIP_END),
+            Some(&SLIP_ESC_ESC) => frame.push(SLIP_ESC),
+            Some(other) => return Err(format!("invalid SLIP escape 0x{:02X}", other)),
+            None => return Err("SLIP frame ends inside an escape".to_string()),
+        }
+    }
+    Ok(frame)
+}
+
+fn cobs_encode(payload: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(payload.len() + payload.len() / 254 + 2);
+    let mut code_index = 0;
+    let mut code = 1u8;
+    encoded.push(0);
+    for &byte in payload {
+        if byte != 0 {
+            encoded.push(byte);
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            code = 1;
+            encoded.push(0);
+        }
+    }
+    encoded[code_index] = code;
+    encoded
+}
+
+fn cobs_decode(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut frame = Vec::with_capacity(raw.len());
+    let mut index = 0;
+    while index < raw.len() {
+        let code = raw[index] as usize;
+        let end = index + code;
+        if end > raw.len() {
+            return Err(format!("COBS block of {} bytes overruns the frame", code - 1));
+        }
+        frame.extend_from_slice(&raw[index + 1..end]);
+        index = end;
+        // A full 254 byte block carries no implicit zero, nor does the last block
+        if code < 0xFF && index < raw.len() {
+            frame.push(0);
+        }
+    }
+    Ok(frame)
+}
+
+impl<I: InterfaceTrait> InterfaceTrait for FramedInterface<I> {
+    fn base_interface(&self) -> &BaseInterface {
+        self.inner.base_interface()
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        self.inner.base_interface_mut()
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.inner.poll_fds()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.frame_ready() || self.inner.has_pending_input()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout);
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout);
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.inner.set_nonblocking(nonblocking);
+    }
+
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.inner.set_reconnect_policy(policy);
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        self.inner.open()?;
+        self.rx_buffer.clear();
+        self.rx_frame = None;
+        self.resync = false;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        self.rx_buffer.clear();
+        self.rx_frame = None;
+        self.resync = false;
+        self.inner.close()
+    }
+
+    // Returns 0 once the wrapped interface has no more data and no complete frame is left;
+    // a partial frame stays buffered across timeouts, a frame too big for the buffer fails
+    // with Overflow and is kept for the next read
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let mut chunk = [0u8; READ_CHUNK];
+        loop {
+            match self.rx_frame.take().map(Ok).or_else(|| self.extract_frame()) {
+                Some(Ok(frame)) => {
+                    if frame.len() > buffer.len() {
+                        let message = format!("{} byte frame does not fit a {} byte buffer", frame.len(), buffer.len());
+                        self.rx_frame = Some(frame);
+                        return Err(self.inner.base_interface_mut().raise_with(InterfaceErrorKind::Overflow, message));
+                    }
+                    buffer[..frame.len()].copy_from_slice(&frame);
+                    return Ok(frame.len() as u32);
+                }
+                Some(Err(reason)) => {
+                    return Err(self.inner.base_interface_mut().raise_with(InterfaceErrorKind::FramingError, reason));
+                }
+                None => {}
+            }
+            let bytes_read = self.inner.read(&mut chunk)? as usize;
+            if bytes_read == 0 {
+                return Ok(0);
+            }
+            self.rx_buffer.extend_from_slice(&chunk[..bytes_read]);
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        let encoded = self.encode(buffer)?;
+        self.inner.write(&encoded)
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode, InterfaceProtocol,
+            InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+struct Channel {
+    // Messages waiting for each end, indexed by the reading side
+    queues: [VecDeque<Vec<u8>>; 2],
+    // An end that was closed, cleared again when it is reopened
+    closed: [bool; 2],
+}
+
+struct Shared {
+    channel: Mutex<Channel>,
+    changed: Condvar,
+}
+
+// One end of an in-memory connection: every write turns into exactly one read on the other
+// end, in order. Lets code taking an InterfaceTrait be tested without files or sockets.
+pub struct LoopbackInterface {
+    shared: Arc<Shared>,
+    side: usize,
+    base_interface: BaseInterface,
+}
+
+impl LoopbackInterface {
+    pub fn pair(first_name: String, second_name: String, log_if: Option<bool>) -> (LoopbackInterface, LoopbackInterface) {
+        let shared = Arc::new(Shared {
+            channel: Mutex::new(Channel { queues: [VecDeque::new(), VecDeque::new()], closed: [false, false] }),
+            changed: Condvar::new(),
+        });
+        let end = |side: usize, name: String| LoopbackInterface {
+            shared: shared.clone(),
+            side,
+            base_interface: BaseInterface::new(name,
+                                            "In-memory loopback".to_string(),
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::Pipe},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        };
+        (end(0, first_name), end(1, second_name))
+    }
+
+    // Messages written by the other end and not read yet
+    pub fn get_pending_count(&self) -> usize {
+        self.lock().queues[self.side].len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Channel> {
+        self.shared.channel.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn peer(&self) -> usize {
+        1 - self.side
+    }
+
+    fn check_open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        Ok(())
+    }
+
+    fn peer_closed(&mut self) -> InterfaceError {
+        self.base_interface.set_event(InterfaceEvent::ConnectionLost);
+        self.base_interface.raise(InterfaceErrorKind::ConnectionLost)
+    }
+}
+
+impl InterfaceTrait for LoopbackInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.get_pending_count() > 0
+    }
+
+    // Messages the other end wrote before this one was opened are kept
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let side = self.side;
+        self.lock().closed[side] = false;
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        self.check_open()?;
+        let side = self.side;
+        let mut channel = self.lock();
+        channel.closed[side] = true;
+        channel.queues[side].clear();
+        drop(channel);
+        self.shared.changed.notify_all();
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    // Reads one message; a buffer too small for it fails with Overflow and leaves the message
+    // queued. Once the other end is closed and nothing is left, reads fail with ConnectionLost.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        self.check_open()?;
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        let (side, peer) = (self.side, self.peer());
+        let shared = self.shared.clone();
+        let mut channel = shared.channel.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(message) = channel.queues[side].front() {
+                if message.len() > buffer.len() {
+                    drop(channel);
+                    return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+                }
+                let message = channel.queues[side].pop_front().unwrap();
+                drop(channel);
+                buffer[..message.len()].copy_from_slice(&message);
+                self.base_interface.error = None;
+                self.base_interface.data_received(message.len());
+                return Ok(message.len() as u32);
+            }
+            if channel.closed[peer] {
+                drop(channel);
+                return Err(self.peer_closed());
+            }
+            channel = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining == Duration::ZERO {
+                        drop(channel);
+                        return Err(self.base_interface.timed_out());
+                    }
+                    shared.changed.wait_timeout(channel, remaining).unwrap_or_else(|e| e.into_inner()).0
+                }
+                None => shared.changed.wait(channel).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+
+    // Never blocks, the other end queues everything until it is read
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        self.check_open()?;
+        let peer = self.peer();
+        let mut channel = self.lock();
+        if channel.closed[peer] {
+            drop(channel);
+            return Err(self.peer_closed());
+        }
+        channel.queues[peer].push_back(buffer.to_vec());
+        drop(channel);
+        self.shared.changed.notify_all();
+        self.base_interface.error = None;
+        self.base_interface.data_sent(buffer.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_pair() -> (LoopbackInterface, LoopbackInterface) {
+        let (mut first, mut second) = LoopbackInterface::pair("first".to_string(), "second".to_string(), Some(true));
+        first.open().unwrap();
+        second.open().unwrap();
+        (first, second)
+    }
+
+    #[test]
+    fn write_turns_into_one_read_on_the_peer() {
+        let (mut first, mut second) = open_pair();
+        first.write(b"hello").unwrap();
+        first.write(b"world").unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(second.read(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"hello");
+        assert_eq!(second.read(&mut buffer).unwrap(), 5);
+        assert_eq!(&buffer[..5], b"world");
+        assert_eq!(second.get_pending_count(), 0);
+        assert_eq!(first.get_pending_count(), 0);
+        second.set_nonblocking(true);
+        assert_eq!(second.read(&mut buffer).unwrap_err().kind(), InterfaceErrorKind::Timeout);
+    }
+
+    #[test]
+    fn undersized_buffer_overflows_and_keeps_the_message() {
+        let (mut first, mut second) = open_pair();
+        first.write(b"0123456789").unwrap();
+        let mut small = [0u8; 4];
+        assert_eq!(second.read(&mut small).unwrap_err().kind(), InterfaceErrorKind::Overflow);
+        assert_eq!(second.get_pending_count(), 1);
+        let mut buffer = [0u8; 16];
+        assert_eq!(second.read(&mut buffer).unwrap(), 10);
+        assert_eq!(&buffer[..10], b"0123456789");
+    }
+
+    #[test]
+    fn read_after_peer_closed_loses_the_connection() {
+        let (mut first, mut second) = open_pair();
+        first.close().unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(second.read(&mut buffer).unwrap_err().kind(), InterfaceErrorKind::ConnectionLost);
+        assert_eq!(second.write(b"late").unwrap_err().kind(), InterfaceErrorKind::ConnectionLost);
+        assert_eq!(second.base_interface().get_stats().get_connections_lost(), 2);
+    }
+}
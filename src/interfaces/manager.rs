@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use super::{InterfaceError, InterfaceErrorKind, InterfaceEventRecord, InterfaceMode, InterfaceProtocol,
+            InterfaceStats, InterfaceStatus, InterfaceTrait, InterfaceType, IsInterfaceManager,
+            SubscriptionId};
+
+// Handles stay valid for the lifetime of the manager and are never reused,
+// so a stale handle can not silently point to another interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InterfaceHandle(u32);
+
+impl InterfaceHandle {
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+struct ManagedInterface {
+    handle: InterfaceHandle,
+    interface: Box<dyn InterfaceTrait>,
+    // Subscriptions made on behalf of subscribe_all, undone when the interface is removed
+    subscriptions: Vec<SubscriptionId>,
+}
+
+pub struct InterfaceManager {
+    interfaces: Vec<ManagedInterface>,
+    next_handle: u32,
+    epoll: Option<OwnedFd>,
+    registered: HashMap<RawFd, InterfaceHandle>,
+    always_readable: Vec<InterfaceHandle>,
+    event_senders: Vec<Sender<InterfaceEventRecord>>,
+}
+
+// How often interfaces without a descriptor to wait on are checked while polling
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_EVENTS: usize = 64;
+
+impl Default for InterfaceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterfaceManager {
+    pub fn new() -> Self {
+        InterfaceManager {
+            interfaces: Vec::new(),
+            next_handle: 0,
+            epoll: None,
+            registered: HashMap::new(),
+            always_readable: Vec::new(),
+            event_senders: Vec::new(),
+        }
+    }
+
+    fn position(&self, handle: InterfaceHandle) -> Option<usize> {
+        self.interfaces.iter().position(|managed| managed.handle == handle)
+    }
+
+    pub fn get(&self, handle: InterfaceHandle) -> Option<&dyn InterfaceTrait> {
+        let position = self.position(handle)?;
+        Some(self.interfaces[position].interface.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: InterfaceHandle) -> Option<&mut dyn InterfaceTrait> {
+        let position = self.position(handle)?;
+        Some(self.interfaces[position].interface.as_mut())
+    }
+
+    pub fn handle_of(&self, name: &str) -> Option<InterfaceHandle> {
+        self.interfaces
+            .iter()
+            .find(|managed| managed.interface.base_interface().get_name() == name)
+            .map(|managed| managed.handle)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&dyn InterfaceTrait> {
+        self.get(self.handle_of(name)?)
+    }
+
+    pub fn get_by_name_mut(&mut self, name: &str) -> Option<&mut dyn InterfaceTrait> {
+        self.get_mut(self.handle_of(name)?)
+    }
+
+    pub fn remove_by_name(&mut self, name: &str) -> Result<Box<dyn InterfaceTrait>, InterfaceError> {
+        match self.handle_of(name) {
+            Some(handle) => self.remove_interface(handle),
+            None => Err(InterfaceError::new(InterfaceErrorKind::UnknownInterface, name)),
+        }
+    }
+
+    pub fn handles(&self) -> Vec<InterfaceHandle> {
+        self.interfaces.iter().map(|managed| managed.handle).collect()
+    }
+
+    pub fn find_by_type(&self, interface_type: &InterfaceType) -> Vec<InterfaceHandle> {
+        self.interfaces
+            .iter()
+            .filter(|managed| managed.interface.base_interface().get_type() == *interface_type)
+            .map(|managed| managed.handle)
+            .collect()
+    }
+
+    pub fn find_by_protocol(&self, protocol: &InterfaceProtocol) -> Vec<InterfaceHandle> {
+        self.interfaces
+            .iter()
+            .filter(|managed| managed.interface.base_interface().get_protocol() == *protocol)
+            .map(|managed| managed.handle)
+            .collect()
+    }
+
+    pub fn status(&self, handle: InterfaceHandle) -> Option<InterfaceStatus> {
+        self.get(handle).map(|interface| interface.base_interface().get_status())
+    }
+
+    // Name and status of every interface, in the order they were added
+    pub fn statuses(&self) -> Vec<(String, InterfaceStatus)> {
+        self.interfaces
+            .iter()
+            .map(|managed| {
+                let base_interface = managed.interface.base_interface();
+                (base_interface.get_name(), base_interface.get_status())
+            })
+            .collect()
+    }
+
+    pub fn stats(&self, handle: InterfaceHandle) -> Option<&InterfaceStats> {
+        self.get(handle).map(|interface| interface.base_interface().get_stats())
+    }
+
+    // Snapshot of every interface's counters, in the order they were added
+    pub fn all_stats(&self) -> Vec<(String, InterfaceStats)> {
+        self.interfaces
+            .iter()
+            .map(|managed| {
+                let base_interface = managed.interface.base_interface();
+                (base_interface.get_name(), base_interface.get_stats().clone())
+            })
+            .collect()
+    }
+
+    pub fn reset_all_stats(&mut self) {
+        for managed in self.interfaces.iter_mut() {
+            managed.interface.base_interface_mut().reset_stats();
+        }
+    }
+
+    // Events of every managed interface, including the ones added later, merged into one stream
+    pub fn subscribe_all(&mut self) -> Receiver<InterfaceEventRecord> {
+        let (sender, receiver) = mpsc::channel();
+        for managed in self.interfaces.iter_mut() {
+            let id = managed.interface.base_interface_mut().subscribers.add_sender(sender.clone());
+            managed.subscriptions.push(id);
+        }
+        self.event_senders.push(sender);
+        receiver
+    }
+
+    pub fn open(&mut self, handle: InterfaceHandle) -> Result<(), InterfaceError> {
+        match self.get_mut(handle) {
+            Some(interface) => interface.open(),
+            None => Err(Self::unknown_handle(handle)),
+        }
+    }
+
+    pub fn close(&mut self, handle: InterfaceHandle) -> Result<(), InterfaceError> {
+        match self.get_mut(handle) {
+            Some(interface) => interface.close(),
+            None => Err(Self::unknown_handle(handle)),
+        }
+    }
+
+    fn unknown_handle(handle: InterfaceHandle) -> InterfaceError {
+        InterfaceError::new(InterfaceErrorKind::UnknownInterface, &format!("#{}", handle.id()))
+    }
+
+    fn epoll_error(&self, e: io::Error) -> InterfaceError {
+        InterfaceError::with_source(InterfaceErrorKind::from_io(e.kind()), "manager", e)
+    }
+
+    // Brings the epoll set in line with the descriptors the interfaces currently use.
+    // A descriptor closed by its interface leaves the set on its own, and a number that got
+    // reused is registered again when MOD finds nothing to modify.
+    fn sync_epoll(&mut self) -> Result<RawFd, InterfaceError> {
+        if self.epoll.is_none() {
+            let epoll_fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+            if epoll_fd < 0 {
+                return Err(self.epoll_error(io::Error::last_os_error()));
+            }
+            self.epoll = Some(unsafe { OwnedFd::from_raw_fd(epoll_fd) });
+        }
+        let epoll_fd = self.epoll.as_ref().unwrap().as_raw_fd();
+        let mut current: HashMap<RawFd, InterfaceHandle> = HashMap::new();
+        for managed in self.interfaces.iter() {
+            if managed.interface.base_interface().get_mode() == InterfaceMode::Write {
+                continue;
+            }
+            for fd in managed.interface.poll_fds() {
+                current.insert(fd, managed.handle);
+            }
+        }
+        for fd in self.registered.keys() {
+            if !current.contains_key(fd) {
+                unsafe { libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_DEL, *fd, std::ptr::null_mut()) };
+            }
+        }
+        self.always_readable.clear();
+        for (fd, handle) in current.iter() {
+            let mut event = libc::epoll_event { events: libc::EPOLLIN as u32, u64: handle.id() as u64 };
+            if unsafe { libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_MOD, *fd, &mut event) } == 0 {
+                continue;
+            }
+            if io::Error::last_os_error().raw_os_error() == Some(libc::ENOENT)
+                && unsafe { libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, *fd, &mut event) } == 0 {
+                continue;
+            }
+            let e = io::Error::last_os_error();
+            // Regular files can not be waited on, like poll(2) they count as always readable
+            if e.raw_os_error() == Some(libc::EPERM) {
+                self.always_readable.push(*handle);
+                continue;
+            }
+            return Err(self.epoll_error(e));
+        }
+        self.registered = current;
+        Ok(epoll_fd)
+    }
+
+    fn pending_handles(&self) -> Vec<InterfaceHandle> {
+        self.interfaces
+            .iter()
+            .filter(|managed| {
+                self.always_readable.contains(&managed.handle) || managed.interface.has_pending_input()
+            })
+            .map(|managed| managed.handle)
+            .collect()
+    }
+
+    // Waits until at least one interface has input to read, or `timeout` ran out (None waits
+    // forever). Returns the readable interfaces in the order they were added, empty on timeout.
+    pub fn poll(&mut self, timeout: Option<Duration>) -> Result<Vec<InterfaceHandle>, InterfaceError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let epoll_fd = self.sync_epoll()?;
+        // Interfaces without descriptors can only be checked, so then wait in short slices
+        let without_fds = self.interfaces.iter().any(|managed| {
+            managed.interface.base_interface().get_status() == InterfaceStatus::Connected
+                && managed.interface.poll_fds().is_empty()
+        });
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+        loop {
+            let mut ready = self.pending_handles();
+            if !ready.is_empty() {
+                return Ok(ready);
+            }
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let slice = match (remaining, without_fds) {
+                (Some(remaining), true) => Some(remaining.min(POLL_INTERVAL)),
+                (None, true) => Some(POLL_INTERVAL),
+                (remaining, false) => remaining,
+            };
+            let timeout_ms = match slice {
+                Some(slice) => slice.as_nanos().div_ceil(1_000_000).min(libc::c_int::MAX as u128) as libc::c_int,
+                None => -1,
+            };
+            let count = unsafe {
+                libc::epoll_wait(epoll_fd, events.as_mut_ptr(), MAX_EVENTS as libc::c_int, timeout_ms)
+            };
+            if count < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(self.epoll_error(e));
+            }
+            for event in events.iter().take(count as usize) {
+                let handle = InterfaceHandle(event.u64 as u32);
+                if !ready.contains(&handle) {
+                    ready.push(handle);
+                }
+            }
+            if !ready.is_empty() {
+                ready.sort_by_key(|handle| self.position(*handle));
+                return Ok(ready);
+            }
+            if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                return Ok(ready);
+            }
+        }
+    }
+
+    fn error_report(errors: Vec<InterfaceError>) -> Result<(), Vec<InterfaceError>> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl IsInterfaceManager for InterfaceManager {
+    fn add_interface(&mut self, mut interface: Box<dyn InterfaceTrait>) -> Result<InterfaceHandle, InterfaceError> {
+        let name = interface.base_interface().get_name();
+        if self.handle_of(&name).is_some() {
+            return Err(InterfaceError::new(InterfaceErrorKind::DuplicateInterface, &name));
+        }
+        let handle = InterfaceHandle(self.next_handle);
+        self.next_handle += 1;
+        let subscriptions = self.event_senders
+            .iter()
+            .map(|sender| interface.base_interface_mut().subscribers.add_sender(sender.clone()))
+            .collect();
+        self.interfaces.push(ManagedInterface { handle, interface, subscriptions });
+        Ok(handle)
+    }
+
+    // The interface is handed back as is, close it first if it should not stay open
+    fn remove_interface(&mut self, handle: InterfaceHandle) -> Result<Box<dyn InterfaceTrait>, InterfaceError> {
+        match self.position(handle) {
+            Some(position) => {
+                let mut managed = self.interfaces.remove(position);
+                for id in managed.subscriptions {
+                    managed.interface.base_interface_mut().unsubscribe(id);
+                }
+                Ok(managed.interface)
+            }
+            None => Err(Self::unknown_handle(handle)),
+        }
+    }
+
+    fn get_interface(&self, index: u32) -> Option<&dyn InterfaceTrait> {
+        self.interfaces.get(index as usize).map(|managed| managed.interface.as_ref())
+    }
+
+    fn get_interface_mut(&mut self, index: u32) -> Option<&mut dyn InterfaceTrait> {
+        let managed = self.interfaces.get_mut(index as usize)?;
+        Some(managed.interface.as_mut())
+    }
+
+    fn get_interface_count(&self) -> u32 {
+        self.interfaces.len() as u32
+    }
+
+    // Tries every interface that is not open yet, one failure does not stop the others
+    fn open_all_interfaces(&mut self) -> Result<(), Vec<InterfaceError>> {
+        let mut errors = Vec::new();
+        for managed in self.interfaces.iter_mut() {
+            if let InterfaceStatus::Connected = managed.interface.base_interface().get_status() {
+                continue;
+            }
+            if let Err(e) = managed.interface.open() {
+                errors.push(e);
+            }
+        }
+        Self::error_report(errors)
+    }
+
+    fn close_all_interfaces(&mut self) -> Result<(), Vec<InterfaceError>> {
+        let mut errors = Vec::new();
+        for managed in self.interfaces.iter_mut() {
+            if let InterfaceStatus::Disconnected = managed.interface.base_interface().get_status() {
+                continue;
+            }
+            if let Err(e) = managed.interface.close() {
+                errors.push(e);
+            }
+        }
+        Self::error_report(errors)
+    }
+}
@@ -0,0 +1,243 @@
+use std::ffi::CString;
+use std::io::ErrorKind;
+use std::os::fd::RawFd;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol,
+            InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+pub struct MessageQueueInterface {
+    queue_name: String,
+    queue: Option<libc::mqd_t>,
+    max_messages: Option<i64>,
+    max_message_size: Option<i64>,
+    message_size: usize,
+    send_priority: u32,
+    receive_buffer: Vec<u8>,
+    // Length and priority of a message left in receive_buffer because it did not fit the
+    // caller's buffer, handed out by the next read
+    pending: Option<(usize, u32)>,
+    unlink_on_close: bool,
+    base_interface: BaseInterface,
+}
+
+impl MessageQueueInterface {
+    pub fn new(name: String, description: String, queue_name: String, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        if !queue_name.starts_with('/') || queue_name[1..].contains('/') {
+            panic!("Invalid message queue name");
+        }
+        MessageQueueInterface {
+            queue_name,
+            queue: None,
+            max_messages: None,
+            max_message_size: None,
+            message_size: 0,
+            send_priority: 0,
+            receive_buffer: Vec::new(),
+            pending: None,
+            unlink_on_close: false,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::MessageQueue},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    // Only used when the queue does not exist yet, an existing queue keeps its attributes
+    pub fn set_queue_attributes(&mut self, max_messages: i64, max_message_size: i64) {
+        self.max_messages = Some(max_messages);
+        self.max_message_size = Some(max_message_size);
+    }
+
+    pub fn set_send_priority(&mut self, priority: u32) {
+        self.send_priority = priority;
+    }
+
+    pub fn set_unlink_on_close(&mut self, unlink_on_close: bool) {
+        self.unlink_on_close = unlink_on_close;
+    }
+
+    // Maximum message size of the opened queue
+    pub fn message_size(&self) -> usize {
+        self.message_size
+    }
+
+    // Returns the number of bytes read and the priority the message was sent with. A message
+    // too big for the buffer fails with Overflow and is kept for the next read.
+    pub fn read_with_priority(&mut self, buffer: &mut [u8]) -> Result<(u32, u32), InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        let queue = match (self.base_interface.get_status(), self.queue) {
+            (InterfaceStatus::Connected, Some(queue)) => queue,
+            _ => {
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+            }
+        };
+        let (received, priority) = match self.pending.take() {
+            Some(pending) => pending,
+            None => self.receive(queue)?,
+        };
+        if received > buffer.len() {
+            self.pending = Some((received, priority));
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..received].copy_from_slice(&self.receive_buffer[..received]);
+        self.base_interface.data_received(received);
+        Ok((received as u32, priority))
+    }
+
+    // mq_receive rejects buffers smaller than the queue message size, so the message is
+    // received into our own buffer and only then checked against the caller's
+    fn receive(&mut self, queue: libc::mqd_t) -> Result<(usize, u32), InterfaceError> {
+        // A message queue descriptor is a file descriptor on Linux, so it can be polled
+        self.base_interface.wait_ready(queue, false)?;
+        self.base_interface.error = None;
+        let mut priority: libc::c_uint = 0;
+        loop {
+            let ret = unsafe {
+                libc::mq_receive(queue,
+                                 self.receive_buffer.as_mut_ptr() as *mut libc::c_char,
+                                 self.receive_buffer.len(),
+                                 &mut priority)
+            };
+            if ret >= 0 {
+                return Ok((ret as usize, priority));
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != ErrorKind::Interrupted {
+                return Err(self.base_interface.raise_io(e));
+            }
+        }
+    }
+
+    pub fn write_with_priority(&mut self, buffer: &[u8], priority: u32) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        let queue = match (self.base_interface.get_status(), self.queue) {
+            (InterfaceStatus::Connected, Some(queue)) => queue,
+            _ => {
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+            }
+        };
+        if buffer.len() > self.message_size {
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        self.base_interface.wait_ready(queue, true)?;
+        self.base_interface.error = None;
+        loop {
+            let ret = unsafe {
+                libc::mq_send(queue, buffer.as_ptr() as *const libc::c_char, buffer.len(), priority)
+            };
+            if ret == 0 {
+                break;
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != ErrorKind::Interrupted {
+                return Err(self.base_interface.raise_io(e));
+            }
+        }
+        self.base_interface.data_sent(buffer.len());
+        Ok(())
+    }
+}
+
+impl InterfaceTrait for MessageQueueInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.queue.into_iter().collect()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let queue_name = CString::new(self.queue_name.as_str())
+            .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+        let access = match self.base_interface.get_mode() {
+            InterfaceMode::Read => libc::O_RDONLY,
+            InterfaceMode::Write => libc::O_WRONLY,
+            InterfaceMode::ReadWrite => libc::O_RDWR,
+        };
+        let queue = unsafe {
+            match (self.max_messages, self.max_message_size) {
+                (Some(max_messages), Some(max_message_size)) => {
+                    let mut attr: libc::mq_attr = std::mem::zeroed();
+                    attr.mq_maxmsg = max_messages as _;
+                    attr.mq_msgsize = max_message_size as _;
+                    libc::mq_open(queue_name.as_ptr(), access | libc::O_CREAT, 0o660 as libc::mode_t,
+                                  &mut attr as *mut libc::mq_attr)
+                }
+                _ => libc::mq_open(queue_name.as_ptr(), access | libc::O_CREAT, 0o660 as libc::mode_t,
+                                   std::ptr::null_mut::<libc::mq_attr>()),
+            }
+        };
+        if queue < 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+        if unsafe { libc::mq_getattr(queue, &mut attr) } != 0 {
+            let e = std::io::Error::last_os_error();
+            unsafe { libc::mq_close(queue) };
+            return Err(self.base_interface.raise_io(e));
+        }
+        self.message_size = attr.mq_msgsize as usize;
+        self.receive_buffer = vec![0; self.message_size];
+        self.queue = Some(queue);
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.pending = None;
+        if let Some(queue) = self.queue.take() {
+            self.base_interface.status = InterfaceStatus::Disconnected;
+            if unsafe { libc::mq_close(queue) } != 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+            if self.unlink_on_close {
+                let queue_name = CString::new(self.queue_name.as_str())
+                    .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+                if unsafe { libc::mq_unlink(queue_name.as_ptr()) } != 0 {
+                    return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+                }
+            }
+            Ok(())
+        } else {
+            Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let (bytes_read, _) = self.read_with_priority(buffer)?;
+        Ok(bytes_read)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        self.write_with_priority(buffer, self.send_priority)
+    }
+}
+
+impl Drop for MessageQueueInterface {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            unsafe { libc::mq_close(queue) };
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol, InterfaceStatus,
+            InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockCall {
+    Open,
+    Close,
+    Read,
+    Write,
+}
+
+impl fmt::Display for MockCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            MockCall::Open => "open",
+            MockCall::Close => "close",
+            MockCall::Read => "read",
+            MockCall::Write => "write",
+        };
+        f.write_str(text)
+    }
+}
+
+struct Expectation {
+    call: MockCall,
+    // Handed out by a read, compared against a write
+    data: Vec<u8>,
+    error: Option<InterfaceErrorKind>,
+}
+
+// Plays a script of expected calls: every open, close, read and write has to match the next
+// expectation in order. Reads hand out canned data, writes are checked against the expected
+// bytes, and any call can be made to fail with a given error kind. Calls that do not match
+// fail with GenericError and are kept for assert_satisfied to report.
+pub struct MockInterface {
+    expectations: VecDeque<Expectation>,
+    calls: Vec<MockCall>,
+    mismatches: Vec<String>,
+    base_interface: BaseInterface,
+}
+
+impl MockInterface {
+    pub fn new(name: String, log_if: Option<bool>) -> Self {
+        MockInterface {
+            expectations: VecDeque::new(),
+            calls: Vec::new(),
+            mismatches: Vec::new(),
+            base_interface: BaseInterface::new(name,
+                                            "Scripted mock".to_string(),
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::Pipe},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn expect_open(&mut self) {
+        self.push(MockCall::Open, Vec::new(), None);
+    }
+
+    pub fn expect_close(&mut self) {
+        self.push(MockCall::Close, Vec::new(), None);
+    }
+
+    pub fn expect_read(&mut self, data: &[u8]) {
+        self.push(MockCall::Read, data.to_vec(), None);
+    }
+
+    pub fn expect_write(&mut self, data: &[u8]) {
+        self.push(MockCall::Write, data.to_vec(), None);
+    }
+
+    // The next call, which has to be `call`, fails with `kind` whatever it was given
+    pub fn expect_error(&mut self, call: MockCall, kind: InterfaceErrorKind) {
+        self.push(call, Vec::new(), Some(kind));
+    }
+
+    // Every call made so far in order, matching or not
+    pub fn get_calls(&self) -> &[MockCall] {
+        &self.calls
+    }
+
+    pub fn get_mismatches(&self) -> &[String] {
+        &self.mismatches
+    }
+
+    pub fn get_remaining_count(&self) -> usize {
+        self.expectations.len()
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.mismatches.is_empty() && self.expectations.is_empty()
+    }
+
+    // Panics listing the mismatched calls and the expectations that were never met
+    #[track_caller]
+    pub fn assert_satisfied(&self) {
+        if self.is_satisfied() {
+            return;
+        }
+        let mut report = format!("mock interface {} not satisfied", self.base_interface.get_name());
+        for mismatch in self.mismatches.iter() {
+            report.push_str(&format!("\n  {}", mismatch));
+        }
+        for expectation in self.expectations.iter() {
+            report.push_str(&format!("\n  {} expected but never made", expectation.call));
+        }
+        panic!("{}", report);
+    }
+
+    fn push(&mut self, call: MockCall, data: Vec<u8>, error: Option<InterfaceErrorKind>) {
+        self.expectations.push_back(Expectation { call, data, error });
+    }
+
+    fn mismatch(&mut self, message: String) -> InterfaceError {
+        self.mismatches.push(message.clone());
+        self.base_interface.raise_with(InterfaceErrorKind::GenericError, message)
+    }
+
+    // Takes the next expectation if it is for `call`, otherwise leaves it for a later call
+    fn next(&mut self, call: MockCall) -> Result<Expectation, InterfaceError> {
+        self.calls.push(call);
+        let number = self.calls.len();
+        let expectation = match self.expectations.front() {
+            Some(expectation) if expectation.call == call => self.expectations.pop_front().unwrap(),
+            Some(expectation) => {
+                let message = format!("call {}: {} where {} was expected", number, call, expectation.call);
+                return Err(self.mismatch(message));
+            }
+            None => return Err(self.mismatch(format!("call {}: unexpected {}", number, call))),
+        };
+        if let Some(kind) = expectation.error {
+            return Err(match kind {
+                InterfaceErrorKind::Timeout => self.base_interface.timed_out(),
+                kind => self.base_interface.raise(kind),
+            });
+        }
+        Ok(expectation)
+    }
+}
+
+impl InterfaceTrait for MockInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    // True while the next expected call is a read, so the manager polls it like a real input
+    fn has_pending_input(&self) -> bool {
+        self.expectations.front().is_some_and(|expectation| expectation.call == MockCall::Read)
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        self.next(MockCall::Open)?;
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        self.next(MockCall::Close)?;
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    // A buffer too small for the canned data fails with Overflow, the read stays expected
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let expectation = self.next(MockCall::Read)?;
+        if expectation.data.len() > buffer.len() {
+            self.expectations.push_front(expectation);
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..expectation.data.len()].copy_from_slice(&expectation.data);
+        self.base_interface.error = None;
+        self.base_interface.data_received(expectation.data.len());
+        Ok(expectation.data.len() as u32)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        let expectation = self.next(MockCall::Write)?;
+        if expectation.data != buffer {
+            let message = format!("call {}: write of {:02x?} where {:02x?} was expected",
+                                  self.calls.len(), buffer, expectation.data);
+            return Err(self.mismatch(message));
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_sent(buffer.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock() -> MockInterface {
+        MockInterface::new("mock".to_string(), Some(true))
+    }
+
+    #[test]
+    fn scripted_calls_in_order_are_satisfied() {
+        let mut interface = mock();
+        interface.expect_open();
+        interface.expect_write(b"ping");
+        interface.expect_read(b"pong");
+        interface.expect_error(MockCall::Read, InterfaceErrorKind::ParityError);
+        interface.expect_close();
+        interface.open().unwrap();
+        interface.write(b"ping").unwrap();
+        let mut buffer = [0u8; 8];
+        assert_eq!(interface.read(&mut buffer).unwrap(), 4);
+        assert_eq!(&buffer[..4], b"pong");
+        assert_eq!(interface.read(&mut buffer).unwrap_err().kind(), InterfaceErrorKind::ParityError);
+        interface.close().unwrap();
+        assert_eq!(interface.get_calls(),
+                   &[MockCall::Open, MockCall::Write, MockCall::Read, MockCall::Read, MockCall::Close]);
+        interface.assert_satisfied();
+    }
+
+    #[test]
+    fn call_out_of_order_is_a_mismatch() {
+        let mut interface = mock();
+        interface.expect_open();
+        interface.expect_write(b"ping");
+        interface.open().unwrap();
+        let mut buffer = [0u8; 8];
+        assert_eq!(interface.read(&mut buffer).unwrap_err().kind(), InterfaceErrorKind::GenericError);
+        assert_eq!(interface.get_mismatches().len(), 1);
+        assert!(interface.get_mismatches()[0].contains("read where write was expected"));
+        // The expectation is still there for the call that should have come
+        interface.write(b"ping").unwrap();
+        assert_eq!(interface.get_remaining_count(), 0);
+        assert!(!interface.is_satisfied());
+    }
+
+    #[test]
+    fn unexpected_write_data_is_a_mismatch() {
+        let mut interface = mock();
+        interface.expect_write(b"ping");
+        assert_eq!(interface.write(b"pong").unwrap_err().kind(), InterfaceErrorKind::GenericError);
+        assert_eq!(interface.get_mismatches().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "close expected but never made")]
+    fn assert_satisfied_panics_on_unmet_expectations() {
+        let mut interface = mock();
+        interface.expect_open();
+        interface.expect_close();
+        interface.open().unwrap();
+        interface.assert_satisfied();
+    }
+}
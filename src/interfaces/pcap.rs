@@ -0,0 +1,754 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::processor_base::processing::DataProcessor;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol, InterfaceStatus,
+            InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_PACKET: u32 = 2;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+const PCAPNG_OPTION_TSOFFSET: u16 = 14;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+// Larger blocks are taken for corruption rather than allocated
+const MAX_BLOCK_LENGTH: usize = 16 * 1024 * 1024;
+const SNAP_LENGTH: u32 = 262_144;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransportProtocol {
+    Udp,
+    Tcp,
+}
+
+impl TransportProtocol {
+    fn number(self) -> u8 {
+        match self {
+            TransportProtocol::Udp => IP_PROTOCOL_UDP,
+            TransportProtocol::Tcp => IP_PROTOCOL_TCP,
+        }
+    }
+}
+
+// Which packets a PcapInterface hands out, every field left None matches anything.
+// Address and port match either end of the packet.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PcapFilter {
+    pub protocol: Option<TransportProtocol>,
+    pub address: Option<IpAddr>,
+    pub port: Option<u16>,
+}
+
+impl PcapFilter {
+    pub fn matches(&self, packet: &CapturedPacket) -> bool {
+        self.protocol.is_none_or(|protocol| protocol == packet.protocol)
+            && self.address.is_none_or(|address| {
+                let address = address.to_canonical();
+                packet.source.ip().to_canonical() == address || packet.destination.ip().to_canonical() == address
+            })
+            && self.port.is_none_or(|port| packet.source.port() == port || packet.destination.port() == port)
+    }
+}
+
+// A UDP datagram or TCP segment payload. TCP segments come out one by one in capture
+// order, neither reassembled nor checked for retransmissions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedPacket {
+    pub timestamp: SystemTime,
+    pub protocol: TransportProtocol,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    pub payload: Vec<u8>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> u16 {
+    let value = [bytes[offset], bytes[offset + 1]];
+    if big_endian { u16::from_be_bytes(value) } else { u16::from_le_bytes(value) }
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> u32 {
+    let value = [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+    if big_endian { u32::from_be_bytes(value) } else { u32::from_le_bytes(value) }
+}
+
+struct NgInterface {
+    link_type: u32,
+    ticks_per_second: u64,
+    offset_seconds: i64,
+}
+
+enum CaptureFormat {
+    Pcap { big_endian: bool, nanos: bool, link_type: u32 },
+    PcapNg { big_endian: bool, interfaces: Vec<NgInterface> },
+}
+
+struct CaptureFrame {
+    timestamp: SystemTime,
+    link_type: u32,
+    data: Vec<u8>,
+}
+
+// Reads raw link layer frames from a pcap or pcapng file
+struct CaptureReader<R: Read> {
+    reader: R,
+    format: CaptureFormat,
+    // Simple packet blocks carry no time of their own, they take the one of the packet before
+    last_timestamp: SystemTime,
+}
+
+impl<R: Read> CaptureReader<R> {
+    fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let format = match u32::from_le_bytes(magic) {
+            PCAPNG_SECTION_HEADER => CaptureFormat::PcapNg { big_endian: false, interfaces: Vec::new() },
+            magic => {
+                let (big_endian, nanos) = match magic {
+                    PCAP_MAGIC_MICROS => (false, false),
+                    PCAP_MAGIC_NANOS => (false, true),
+                    magic if magic.swap_bytes() == PCAP_MAGIC_MICROS => (true, false),
+                    magic if magic.swap_bytes() == PCAP_MAGIC_NANOS => (true, true),
+                    _ => return Err(invalid("not a pcap or pcapng file")),
+                };
+                let mut header = [0u8; 20];
+                reader.read_exact(&mut header)?;
+                CaptureFormat::Pcap { big_endian, nanos, link_type: read_u32(&header, 16, big_endian) & 0x0FFF_FFFF }
+            }
+        };
+        let mut capture = CaptureReader { reader, format, last_timestamp: UNIX_EPOCH };
+        if let CaptureFormat::PcapNg { .. } = capture.format {
+            capture.read_section_header()?;
+        }
+        Ok(capture)
+    }
+
+    // The block type of the section header was already read
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut start = [0u8; 8];
+        self.reader.read_exact(&mut start)?;
+        let big_endian = match u32::from_le_bytes([start[4], start[5], start[6], start[7]]) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid("bad pcapng byte order magic")),
+        };
+        let length = read_u32(&start, 0, big_endian) as usize;
+        if !(28..=MAX_BLOCK_LENGTH).contains(&length) {
+            return Err(invalid("bad pcapng section header length"));
+        }
+        // Version, section length and options are of no interest
+        let mut rest = vec![0u8; length - 12];
+        self.reader.read_exact(&mut rest)?;
+        self.format = CaptureFormat::PcapNg { big_endian, interfaces: Vec::new() };
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> io::Result<Option<CaptureFrame>> {
+        match self.format {
+            CaptureFormat::Pcap { big_endian, nanos, link_type } => {
+                let mut header = [0u8; 16];
+                match self.reader.read_exact(&mut header) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e),
+                }
+                let seconds = read_u32(&header, 0, big_endian) as u64;
+                let fraction = read_u32(&header, 4, big_endian);
+                let length = read_u32(&header, 8, big_endian) as usize;
+                if length > MAX_BLOCK_LENGTH {
+                    return Err(invalid("bad pcap record length"));
+                }
+                let mut data = vec![0u8; length];
+                self.reader.read_exact(&mut data)?;
+                let nanos = if nanos { fraction } else { fraction.saturating_mul(1000) };
+                let timestamp = UNIX_EPOCH + Duration::new(seconds, 0) + Duration::from_nanos(nanos as u64);
+                Ok(Some(CaptureFrame { timestamp, link_type, data }))
+            }
+            CaptureFormat::PcapNg { .. } => self.next_ng_frame(),
+        }
+    }
+
+    fn next_ng_frame(&mut self) -> io::Result<Option<CaptureFrame>> {
+        loop {
+            let mut start = [0u8; 4];
+            match self.reader.read_exact(&mut start) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            if u32::from_le_bytes(start) == PCAPNG_SECTION_HEADER {
+                self.read_section_header()?;
+                continue;
+            }
+            let CaptureFormat::PcapNg { big_endian, interfaces } = &mut self.format else {
+                return Ok(None);
+            };
+            let big_endian = *big_endian;
+            let block_type = read_u32(&start, 0, big_endian);
+            let mut length = [0u8; 4];
+            self.reader.read_exact(&mut length)?;
+            let length = read_u32(&length, 0, big_endian) as usize;
+            if !(12..=MAX_BLOCK_LENGTH).contains(&length) || !length.is_multiple_of(4) {
+                return Err(invalid("bad pcapng block length"));
+            }
+            // Body and the trailing copy of the length
+            let mut body = vec![0u8; length - 8];
+            self.reader.read_exact(&mut body)?;
+            body.truncate(length - 12);
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                    interfaces.push(interface_description(&body, big_endian));
+                }
+                PCAPNG_ENHANCED_PACKET | PCAPNG_PACKET if body.len() >= 20 => {
+                    let interface_id = if block_type == PCAPNG_ENHANCED_PACKET {
+                        read_u32(&body, 0, big_endian) as usize
+                    } else {
+                        read_u16(&body, 0, big_endian) as usize
+                    };
+                    let interface = interfaces.get(interface_id)
+                        .ok_or_else(|| invalid("pcapng packet of an undescribed interface"))?;
+                    let ticks = ((read_u32(&body, 4, big_endian) as u64) << 32) | read_u32(&body, 8, big_endian) as u64;
+                    let captured = (read_u32(&body, 12, big_endian) as usize).min(body.len() - 20);
+                    let timestamp = ng_timestamp(interface, ticks);
+                    self.last_timestamp = timestamp;
+                    return Ok(Some(CaptureFrame {
+                        timestamp,
+                        link_type: interface.link_type,
+                        data: body[20..20 + captured].to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                    let interface = interfaces.first()
+                        .ok_or_else(|| invalid("pcapng packet of an undescribed interface"))?;
+                    let captured = (read_u32(&body, 0, big_endian) as usize).min(body.len() - 4);
+                    return Ok(Some(CaptureFrame {
+                        timestamp: self.last_timestamp,
+                        link_type: interface.link_type,
+                        data: body[4..4 + captured].to_vec(),
+                    }));
+                }
+                // Statistics, name resolution, custom and unknown blocks
+                _ => {}
+            }
+        }
+    }
+}
+
+fn interface_description(body: &[u8], big_endian: bool) -> NgInterface {
+    let mut interface = NgInterface {
+        link_type: read_u16(body, 0, big_endian) as u32,
+        ticks_per_second: 1_000_000,
+        offset_seconds: 0,
+    };
+    let mut offset = 8;
+    while offset + 4 <= body.len() {
+        let code = read_u16(body, offset, big_endian);
+        let length = read_u16(body, offset + 2, big_endian) as usize;
+        let value = &body[offset + 4..(offset + 4 + length).min(body.len())];
+        match code {
+            PCAPNG_OPTION_END => break,
+            PCAPNG_OPTION_TSRESOL if !value.is_empty() => {
+                let exponent = (value[0] & 0x7F) as u32;
+                let ticks = if value[0] & 0x80 == 0 { 10u64.checked_pow(exponent) } else { 1u64.checked_shl(exponent) };
+                interface.ticks_per_second = ticks.unwrap_or(1_000_000).max(1);
+            }
+            PCAPNG_OPTION_TSOFFSET if value.len() >= 8 => {
+                let bytes: [u8; 8] = value[..8].try_into().unwrap();
+                interface.offset_seconds = if big_endian { i64::from_be_bytes(bytes) } else { i64::from_le_bytes(bytes) };
+            }
+            _ => {}
+        }
+        offset += 4 + length.div_ceil(4) * 4;
+    }
+    interface
+}
+
+fn ng_timestamp(interface: &NgInterface, ticks: u64) -> SystemTime {
+    let seconds = ticks / interface.ticks_per_second;
+    let nanos = (ticks % interface.ticks_per_second) as u128 * 1_000_000_000 / interface.ticks_per_second as u128;
+    let timestamp = UNIX_EPOCH + Duration::new(seconds, nanos as u32);
+    if interface.offset_seconds >= 0 {
+        timestamp + Duration::from_secs(interface.offset_seconds as u64)
+    } else {
+        timestamp - Duration::from_secs(interface.offset_seconds.unsigned_abs())
+    }
+}
+
+// Strips the link layer, None for anything that is not IPv4 or IPv6
+fn network_layer(link_type: u32, frame: &[u8]) -> Option<&[u8]> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                offset += 4;
+                ethertype = u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => frame.get(offset + 2..),
+                _ => None,
+            }
+        }
+        LINKTYPE_LINUX_SLL => match u16::from_be_bytes(frame.get(14..16)?.try_into().ok()?) {
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => frame.get(16..),
+            _ => None,
+        },
+        LINKTYPE_LINUX_SLL2 => match u16::from_be_bytes(frame.get(0..2)?.try_into().ok()?) {
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => frame.get(20..),
+            _ => None,
+        },
+        // The address family is in the byte order of the capturing host, AF_INET is 2 everywhere
+        // and AF_INET6 one of 10, 24, 28 or 30
+        LINKTYPE_NULL | LINKTYPE_LOOP => {
+            let family = frame.get(0..4)?;
+            let family = if family[0] == 0 { family[3] } else { family[0] };
+            match family {
+                2 | 10 | 24 | 28 | 30 => frame.get(4..),
+                _ => None,
+            }
+        }
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Some(frame),
+        _ => None,
+    }
+}
+
+// Returns the protocol, the addresses and the transport segment of an unfragmented packet
+fn transport_layer(packet: &[u8]) -> Option<(u8, IpAddr, IpAddr, &[u8])> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_length = ((packet[0] & 0x0F) as usize) * 4;
+            let total_length = u16::from_be_bytes(packet.get(2..4)?.try_into().ok()?) as usize;
+            let fragment = u16::from_be_bytes(packet.get(6..8)?.try_into().ok()?);
+            // More fragments set, or not the first fragment
+            if fragment & 0x3FFF != 0 || header_length < 20 || total_length < header_length {
+                return None;
+            }
+            let source: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = packet.get(16..20)?.try_into().ok()?;
+            // The total length trims the padding of short Ethernet frames
+            let segment = packet.get(header_length..total_length.min(packet.len()))?;
+            Some((packet[9], IpAddr::from(source), IpAddr::from(destination), segment))
+        }
+        6 => {
+            let payload_length = u16::from_be_bytes(packet.get(4..6)?.try_into().ok()?) as usize;
+            let source: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_length).min(packet.len());
+            let mut next_header = packet[6];
+            let mut offset = 40;
+            // Hop-by-hop, routing and destination options, a fragment header ends the search
+            while matches!(next_header, 0 | 43 | 60) {
+                let extension = packet.get(offset..offset + 2)?;
+                next_header = extension[0];
+                offset += (extension[1] as usize + 1) * 8;
+            }
+            let segment = packet.get(offset..end)?;
+            Some((next_header, IpAddr::from(source), IpAddr::from(destination), segment))
+        }
+        _ => None,
+    }
+}
+
+fn decode(frame: &CaptureFrame) -> Option<CapturedPacket> {
+    let (protocol, source_ip, destination_ip, segment) = transport_layer(network_layer(frame.link_type, &frame.data)?)?;
+    let source_port = u16::from_be_bytes(segment.get(0..2)?.try_into().ok()?);
+    let destination_port = u16::from_be_bytes(segment.get(2..4)?.try_into().ok()?);
+    let (protocol, payload) = match protocol {
+        IP_PROTOCOL_UDP => {
+            // A segment cut short of its 8 byte header, e.g. by the snap length, carries no payload
+            if segment.len() < 8 {
+                return None;
+            }
+            let length = u16::from_be_bytes(segment[4..6].try_into().ok()?) as usize;
+            (TransportProtocol::Udp, segment.get(8..length.min(segment.len()).max(8))?)
+        }
+        IP_PROTOCOL_TCP => {
+            let header_length = ((segment.get(12)? >> 4) as usize) * 4;
+            (TransportProtocol::Tcp, segment.get(header_length.max(20)..)?)
+        }
+        _ => return None,
+    };
+    Some(CapturedPacket {
+        timestamp: frame.timestamp,
+        protocol,
+        source: SocketAddr::new(source_ip, source_port),
+        destination: SocketAddr::new(destination_ip, destination_port),
+        payload: payload.to_vec(),
+    })
+}
+
+fn checksum_add(mut sum: u32, bytes: &[u8]) -> u32 {
+    let mut chunks = bytes.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+fn checksum_finish(mut sum: u32) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// Writes classic pcap with nanosecond timestamps and raw IP frames; the IP and transport
+// headers around each payload are made up from the packet addresses
+struct CaptureWriter<W: Write> {
+    writer: W,
+    // Next TCP sequence number per direction, so analysers can follow the stream
+    sequences: HashMap<(SocketAddr, SocketAddr), u32>,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&PCAP_MAGIC_NANOS.to_le_bytes())?;
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&4u16.to_le_bytes())?;
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&SNAP_LENGTH.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_RAW.to_le_bytes())?;
+        Ok(CaptureWriter { writer, sequences: HashMap::new() })
+    }
+
+    fn write_packet(&mut self, packet: &CapturedPacket) -> io::Result<()> {
+        let frame = self.encode(packet)?;
+        let since_epoch = packet.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = u32::try_from(since_epoch.as_secs()).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+        let length = frame.len() as u32;
+        self.writer.write_all(&seconds.to_le_bytes())?;
+        self.writer.write_all(&since_epoch.subsec_nanos().to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&frame)
+    }
+
+    fn encode(&mut self, packet: &CapturedPacket) -> io::Result<Vec<u8>> {
+        let (source, destination) = (packet.source.ip().to_canonical(), packet.destination.ip().to_canonical());
+        let transport_header_length = match packet.protocol {
+            TransportProtocol::Udp => 8,
+            TransportProtocol::Tcp => 20,
+        };
+        let segment_length = transport_header_length + packet.payload.len();
+        let mut segment = Vec::with_capacity(segment_length);
+        segment.extend_from_slice(&packet.source.port().to_be_bytes());
+        segment.extend_from_slice(&packet.destination.port().to_be_bytes());
+        match packet.protocol {
+            TransportProtocol::Udp => {
+                let length = u16::try_from(segment_length).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+                segment.extend_from_slice(&length.to_be_bytes());
+                segment.extend_from_slice(&[0, 0]);
+            }
+            TransportProtocol::Tcp => {
+                let sequence = self.sequences.entry((packet.source, packet.destination)).or_insert(1);
+                segment.extend_from_slice(&sequence.to_be_bytes());
+                *sequence = sequence.wrapping_add(packet.payload.len() as u32);
+                segment.extend_from_slice(&0u32.to_be_bytes());
+                // Header length 5 words, PSH and ACK, full window, checksum, urgent pointer
+                segment.extend_from_slice(&[0x50, 0x18, 0xFF, 0xFF, 0, 0, 0, 0]);
+            }
+        }
+        segment.extend_from_slice(&packet.payload);
+        let checksum_offset = match packet.protocol {
+            TransportProtocol::Udp => 6,
+            TransportProtocol::Tcp => 16,
+        };
+        let mut frame = Vec::with_capacity(40 + segment_length);
+        let pseudo_header_sum = match (source, destination) {
+            (IpAddr::V4(source), IpAddr::V4(destination)) => {
+                let total_length = u16::try_from(20 + segment_length)
+                    .map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+                frame.extend_from_slice(&[0x45, 0]);
+                frame.extend_from_slice(&total_length.to_be_bytes());
+                // No identification, don't fragment, TTL 64, checksum filled in below
+                frame.extend_from_slice(&[0, 0, 0x40, 0, 64, packet.protocol.number(), 0, 0]);
+                frame.extend_from_slice(&source.octets());
+                frame.extend_from_slice(&destination.octets());
+                let header_checksum = checksum_finish(checksum_add(0, &frame));
+                frame[10..12].copy_from_slice(&header_checksum.to_be_bytes());
+                let sum = checksum_add(checksum_add(0, &source.octets()), &destination.octets());
+                sum + packet.protocol.number() as u32 + segment_length as u32
+            }
+            (IpAddr::V6(source), IpAddr::V6(destination)) => {
+                let payload_length = u16::try_from(segment_length).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+                frame.extend_from_slice(&[0x60, 0, 0, 0]);
+                frame.extend_from_slice(&payload_length.to_be_bytes());
+                frame.extend_from_slice(&[packet.protocol.number(), 64]);
+                frame.extend_from_slice(&source.octets());
+                frame.extend_from_slice(&destination.octets());
+                let sum = checksum_add(checksum_add(0, &source.octets()), &destination.octets());
+                sum + packet.protocol.number() as u32 + segment_length as u32
+            }
+            _ => return Err(io::Error::new(ErrorKind::InvalidInput, "source and destination of different IP versions")),
+        };
+        let mut checksum = checksum_finish(checksum_add(pseudo_header_sum, &segment));
+        // An all zero UDP checksum means none was computed
+        if checksum == 0 && packet.protocol == TransportProtocol::Udp {
+            checksum = 0xFFFF;
+        }
+        segment[checksum_offset..checksum_offset + 2].copy_from_slice(&checksum.to_be_bytes());
+        frame.extend_from_slice(&segment);
+        Ok(frame)
+    }
+}
+
+// Read mode hands out the payloads of the UDP and TCP packets in a pcap or pcapng file that
+// pass the filter, one per read. Write mode records everything written as packets of a new
+// pcap file, sent between the write endpoints.
+pub struct PcapInterface {
+    file_path: String,
+    reader: Option<CaptureReader<BufReader<File>>>,
+    writer: Option<CaptureWriter<BufWriter<File>>>,
+    filter: PcapFilter,
+    write_protocol: TransportProtocol,
+    write_source: SocketAddr,
+    write_destination: SocketAddr,
+    packet_count: u64,
+    // A packet too big for the caller's buffer, handed out by the next read
+    pending: Option<CapturedPacket>,
+    base_interface: BaseInterface,
+}
+
+impl PcapInterface {
+    pub fn new(name: String, description: String, file_path: String, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        PcapInterface {
+            file_path,
+            reader: None,
+            writer: None,
+            filter: PcapFilter::default(),
+            write_protocol: TransportProtocol::Udp,
+            write_source: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            write_destination: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            packet_count: 0,
+            pending: None,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::File},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: PcapFilter) {
+        self.filter = filter;
+    }
+
+    pub fn get_filter(&self) -> &PcapFilter {
+        &self.filter
+    }
+
+    // Addresses and protocol the packets made from written data carry, UDP between two
+    // loopback addresses by default
+    pub fn set_write_endpoints(&mut self, protocol: TransportProtocol, source: SocketAddr, destination: SocketAddr) {
+        self.write_protocol = protocol;
+        self.write_source = source;
+        self.write_destination = destination;
+    }
+
+    // Packets handed out so far, matching the filter
+    pub fn get_packet_count(&self) -> u64 {
+        self.packet_count
+    }
+
+    // The next packet that passes the filter, None at the end of the capture
+    pub fn read_packet(&mut self) -> Result<Option<CapturedPacket>, InterfaceError> {
+        let packet = match self.next_packet()? {
+            Some(packet) => packet,
+            None => return Ok(None),
+        };
+        self.handed_out(&packet);
+        Ok(Some(packet))
+    }
+
+    // Only counted once handed out, a packet kept after an Overflow is not counted twice
+    fn handed_out(&mut self, packet: &CapturedPacket) {
+        self.packet_count += 1;
+        self.base_interface.error = None;
+        self.base_interface.data_received(packet.payload.len());
+    }
+
+    fn next_packet(&mut self) -> Result<Option<CapturedPacket>, InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        if let Some(packet) = self.pending.take() {
+            return Ok(Some(packet));
+        }
+        loop {
+            let reader = match self.reader.as_mut() {
+                Some(reader) => reader,
+                None => return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            let frame = match reader.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(None),
+                // A capture cut short, e.g. tcpdump killed, ends at its last complete packet
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError, e));
+                }
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            };
+            if let Some(packet) = decode(&frame)
+                && self.filter.matches(&packet) {
+                return Ok(Some(packet));
+            }
+        }
+    }
+
+    // The next packet as a frame for the processing blocks: the destination port goes into
+    // ifcode, the packet's position among the matching ones into id
+    pub fn read_frame(&mut self) -> Result<Option<DataProcessor>, InterfaceError> {
+        let packet = match self.read_packet()? {
+            Some(packet) => packet,
+            None => return Ok(None),
+        };
+        let since_epoch = packet.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok(Some(DataProcessor::new(packet.destination.port() as u64,
+                                   self.packet_count - 1,
+                                   since_epoch.as_secs(),
+                                   since_epoch.subsec_nanos() as u64,
+                                   packet.payload.len() as u64,
+                                   packet.payload)))
+    }
+
+    pub fn write_packet(&mut self, packet: &CapturedPacket) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+        };
+        match writer.write_packet(packet) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::InvalidInput => {
+                return Err(self.base_interface.raise_with(InterfaceErrorKind::NotValidSocketAddr, e));
+            }
+            Err(e) => return Err(self.base_interface.raise_io(e)),
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_sent(packet.payload.len());
+        Ok(())
+    }
+}
+
+impl InterfaceTrait for PcapInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        match self.base_interface.get_mode() {
+            InterfaceMode::Read => {
+                let file = File::open(&self.file_path).map_err(|e| self.base_interface.raise_io(e))?;
+                let reader = CaptureReader::new(BufReader::new(file)).map_err(|e| match e.kind() {
+                    ErrorKind::InvalidData | ErrorKind::UnexpectedEof => {
+                        self.base_interface.raise_with(InterfaceErrorKind::ProtocolError, e)
+                    }
+                    _ => self.base_interface.raise_io(e),
+                })?;
+                self.reader = Some(reader);
+            }
+            InterfaceMode::Write => {
+                let file = File::create(&self.file_path).map_err(|e| self.base_interface.raise_io(e))?;
+                let writer = CaptureWriter::new(BufWriter::new(file)).map_err(|e| self.base_interface.raise_io(e))?;
+                self.writer = Some(writer);
+            }
+            InterfaceMode::ReadWrite => {
+                return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                                                          "a capture file is either read or written"));
+            }
+        }
+        self.packet_count = 0;
+        self.pending = None;
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.reader = None;
+        self.pending = None;
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        if let Some(mut writer) = self.writer.take() {
+            writer.writer.flush().map_err(|e| self.base_interface.raise_io(e))?;
+        }
+        Ok(())
+    }
+
+    // End of the capture reads 0 bytes, like the end of a FileInterface. A payload too big
+    // for the buffer fails with Overflow and is kept for the next read.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let packet = match self.next_packet()? {
+            Some(packet) => packet,
+            None => return Ok(0),
+        };
+        if packet.payload.len() > buffer.len() {
+            self.pending = Some(packet);
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..packet.payload.len()].copy_from_slice(&packet.payload);
+        self.handed_out(&packet);
+        Ok(packet.payload.len() as u32)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        let packet = CapturedPacket {
+            timestamp: SystemTime::now(),
+            protocol: self.write_protocol,
+            source: self.write_source,
+            destination: self.write_destination,
+            payload: buffer.to_vec(),
+        };
+        self.write_packet(&packet)
+    }
+}
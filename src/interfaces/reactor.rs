@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+const MAX_EVENTS: usize = 64;
+
+#[derive(Default)]
+struct Interest {
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+    registered: bool,
+}
+
+impl Interest {
+    fn events(&self) -> u32 {
+        let mut events = libc::EPOLLONESHOT as u32;
+        if self.reader.is_some() {
+            events |= libc::EPOLLIN as u32;
+        }
+        if self.writer.is_some() {
+            events |= libc::EPOLLOUT as u32;
+        }
+        events
+    }
+}
+
+// One epoll thread shared by every async interface. It only wakes tasks up, whichever
+// executor they run on polls them again, so no particular runtime is needed.
+struct Reactor {
+    epoll: OwnedFd,
+    interests: Mutex<HashMap<RawFd, Interest>>,
+}
+
+static REACTOR: OnceLock<Result<Reactor, i32>> = OnceLock::new();
+
+fn reactor() -> io::Result<&'static Reactor> {
+    let reactor = REACTOR.get_or_init(|| {
+        let epoll_fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epoll_fd < 0 {
+            return Err(io::Error::last_os_error().raw_os_error().unwrap_or(libc::EIO));
+        }
+        let epoll = unsafe { OwnedFd::from_raw_fd(epoll_fd) };
+        thread::Builder::new()
+            .name("interface-reactor".to_string())
+            .spawn(|| {
+                if let Ok(reactor) = REACTOR.wait() {
+                    reactor.run();
+                }
+            })
+            .map_err(|e| e.raw_os_error().unwrap_or(libc::EAGAIN))?;
+        Ok(Reactor { epoll, interests: Mutex::new(HashMap::new()) })
+    });
+    reactor.as_ref().map_err(|errno| io::Error::from_raw_os_error(*errno))
+}
+
+impl Reactor {
+    fn run(&self) {
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+        loop {
+            let count = unsafe {
+                libc::epoll_wait(self.epoll.as_raw_fd(), events.as_mut_ptr(), MAX_EVENTS as libc::c_int, -1)
+            };
+            if count < 0 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return;
+            }
+            // Woken only once the lock is released, an executor polling inline calls register
+            let mut wakers = Vec::new();
+            let mut interests = self.interests.lock().unwrap_or_else(|e| e.into_inner());
+            for event in &events[..count as usize] {
+                let (ready, fd) = (event.events, event.u64 as RawFd);
+                let interest = match interests.get_mut(&fd) {
+                    Some(interest) => interest,
+                    None => continue,
+                };
+                // Errors and hang-ups wake both sides, the retried operation reports them
+                let failed = ready & (libc::EPOLLERR | libc::EPOLLHUP) as u32 != 0;
+                if (failed || ready & libc::EPOLLIN as u32 != 0)
+                    && let Some(reader) = interest.reader.take() {
+                    wakers.push(reader);
+                }
+                if (failed || ready & libc::EPOLLOUT as u32 != 0)
+                    && let Some(writer) = interest.writer.take() {
+                    wakers.push(writer);
+                }
+                // One-shot registrations are disarmed now, re-arm for whoever still waits
+                if interest.reader.is_some() || interest.writer.is_some() {
+                    let _ = self.arm(fd, interest);
+                }
+            }
+            drop(interests);
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+
+    fn arm(&self, fd: RawFd, interest: &mut Interest) -> io::Result<()> {
+        let mut event = libc::epoll_event { events: interest.events(), u64: fd as u64 };
+        let epoll_fd = self.epoll.as_raw_fd();
+        // A descriptor closed and reused since it was last seen is no longer in the set
+        let ops = if interest.registered {
+            [libc::EPOLL_CTL_MOD, libc::EPOLL_CTL_ADD]
+        } else {
+            [libc::EPOLL_CTL_ADD, libc::EPOLL_CTL_MOD]
+        };
+        for op in ops {
+            if unsafe { libc::epoll_ctl(epoll_fd, op, fd, &mut event) } == 0 {
+                interest.registered = true;
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if !matches!(e.raw_os_error(), Some(libc::ENOENT) | Some(libc::EEXIST)) {
+                return Err(e);
+            }
+        }
+        Err(io::Error::last_os_error())
+    }
+
+    fn register(&self, fd: RawFd, write: bool, waker: Waker) -> io::Result<()> {
+        let mut interests = self.interests.lock().unwrap_or_else(|e| e.into_inner());
+        let interest = interests.entry(fd).or_default();
+        if write {
+            interest.writer = Some(waker);
+        } else {
+            interest.reader = Some(waker);
+        }
+        let result = self.arm(fd, interest);
+        if result.is_err() {
+            interests.remove(&fd);
+        }
+        result
+    }
+
+    fn deregister(&self, fd: RawFd) {
+        let mut interests = self.interests.lock().unwrap_or_else(|e| e.into_inner());
+        if interests.remove(&fd).is_some_and(|interest| interest.registered) {
+            unsafe { libc::epoll_ctl(self.epoll.as_raw_fd(), libc::EPOLL_CTL_DEL, fd, std::ptr::null_mut()) };
+        }
+    }
+}
+
+// Runs `operation` on the non-blocking `fd` and, when it would block, parks the task until
+// the reactor sees the descriptor turn ready
+pub(super) fn poll_io<T>(
+    fd: RawFd,
+    write: bool,
+    cx: &mut Context<'_>,
+    mut operation: impl FnMut() -> io::Result<T>,
+) -> Poll<io::Result<T>> {
+    loop {
+        match operation() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            result => return Poll::Ready(result),
+        }
+        // Level triggered, so readiness that came in before the registration is not lost
+        return match reactor().and_then(|reactor| reactor.register(fd, write, cx.waker().clone())) {
+            Ok(()) => Poll::Pending,
+            // Regular files can not be watched but never block either, just try again
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        };
+    }
+}
+
+// Has to run before `fd` is closed, a reused descriptor number would otherwise inherit it
+pub(super) fn deregister(fd: RawFd) {
+    if let Some(Ok(reactor)) = REACTOR.get() {
+        reactor.deregister(fd);
+    }
+}
@@ -0,0 +1,149 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::{InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceStatus, InterfaceTrait};
+
+// Status an interface is left in once its reconnect attempts ran out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GiveUpAction {
+    Disconnect,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    // None keeps retrying for as long as it takes
+    pub max_retries: Option<u32>,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    // Fraction of each delay that is randomised, 0.0 (none) to 1.0
+    pub jitter: f64,
+    pub give_up: GiveUpAction,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_retries: Some(10),
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            give_up: GiveUpAction::Error,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // Delay before attempt `attempt` (0 for the first one), `random` is uniform in [0, 1)
+    pub fn delay(&self, attempt: u32, random: f64) -> Duration {
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(attempt.min(64) as i32);
+        let base = base.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // Spread the delay over [base * (1 - jitter), base] so peers do not retry in lockstep
+        Duration::from_secs_f64(base * (1.0 - jitter * random))
+    }
+}
+
+pub(super) struct ReconnectState {
+    policy: ReconnectPolicy,
+    attempts: u32,
+    next_attempt: Option<Instant>,
+    random_state: u64,
+}
+
+impl ReconnectState {
+    pub(super) fn new(policy: ReconnectPolicy) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_nanos() as u64)
+            .unwrap_or(0);
+        ReconnectState {
+            policy,
+            attempts: 0,
+            next_attempt: None,
+            random_state: seed | 1,
+        }
+    }
+
+    // xorshift64, jitter does not need anything better
+    fn random(&mut self) -> f64 {
+        self.random_state ^= self.random_state << 13;
+        self.random_state ^= self.random_state >> 7;
+        self.random_state ^= self.random_state << 17;
+        (self.random_state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn schedule(&mut self) {
+        let random = self.random();
+        self.next_attempt = Some(Instant::now() + self.policy.delay(self.attempts, random));
+    }
+
+    fn reset(&mut self) {
+        self.attempts = 0;
+        self.next_attempt = None;
+    }
+}
+
+// Called by an interface that lost its connection: reopens it following the reconnect policy.
+// Blocking interfaces wait out the backoff here; with a timeout or in non-blocking mode an
+// attempt is only made once it is due, ConnectionLost is returned in the meantime.
+pub(super) fn recover<I: InterfaceTrait + ?Sized>(interface: &mut I) -> Result<(), InterfaceError> {
+    let base_interface = interface.base_interface_mut();
+    if base_interface.get_status() != InterfaceStatus::Reconnecting {
+        base_interface.status = if base_interface.reconnect.is_some() {
+            InterfaceStatus::Reconnecting
+        } else {
+            InterfaceStatus::Disconnected
+        };
+        base_interface.set_event(InterfaceEvent::ConnectionLost);
+        if let Some(state) = base_interface.reconnect.as_mut() {
+            state.reset();
+            state.schedule();
+        }
+    }
+    let blocking = base_interface.wait_timeout(false).is_none();
+    loop {
+        let base_interface = interface.base_interface_mut();
+        let state = match base_interface.reconnect.as_mut() {
+            Some(state) => state,
+            None => return Err(base_interface.raise(InterfaceErrorKind::ConnectionLost)),
+        };
+        if state.policy.max_retries.is_some_and(|max_retries| state.attempts >= max_retries) {
+            base_interface.status = match state.policy.give_up {
+                GiveUpAction::Disconnect => InterfaceStatus::Disconnected,
+                GiveUpAction::Error => InterfaceStatus::Error,
+            };
+            state.reset();
+            return Err(base_interface.raise(InterfaceErrorKind::ConnectionLost));
+        }
+        let next_attempt = state.next_attempt.unwrap_or_else(Instant::now);
+        let now = Instant::now();
+        if next_attempt > now {
+            if !blocking {
+                return Err(base_interface.raise_quiet(InterfaceErrorKind::ConnectionLost));
+            }
+            thread::sleep(next_attempt - now);
+        }
+        // Whatever is left of the old connection goes first, its close result is of no interest
+        let _ = interface.close();
+        let reopened = interface.open();
+        let base_interface = interface.base_interface_mut();
+        let state = base_interface.reconnect.as_mut().unwrap();
+        match reopened {
+            Ok(()) => {
+                state.reset();
+                base_interface.stats.record_reconnect();
+                // open already reported ConnectionEstablished
+                base_interface.status = InterfaceStatus::Connected;
+                return Ok(());
+            }
+            Err(_) => {
+                state.attempts += 1;
+                state.schedule();
+                base_interface.status = InterfaceStatus::Reconnecting;
+            }
+        }
+    }
+}
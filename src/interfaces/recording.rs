@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::os::fd::RawFd;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{BaseInterface, InterfaceError, InterfaceTrait, ReconnectPolicy};
+
+// Recording files start with MAGIC and a little endian u16 version, followed by entries:
+//   0x00 name  id: u16, length: u16, interface name (UTF-8)
+//   0x01 data  id: u16, direction: u8, timestamp: u64 ns since the Unix epoch, length: u32, bytes
+// A name entry precedes the first data entry of each interface, so names are stored only once.
+const MAGIC: &[u8; 6] = b"IFREC\0";
+const VERSION: u16 = 1;
+const ENTRY_NAME: u8 = 0x00;
+const ENTRY_DATA: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrafficDirection {
+    Received,
+    Sent,
+}
+
+impl TrafficDirection {
+    fn to_byte(self) -> u8 {
+        match self {
+            TrafficDirection::Received => 0,
+            TrafficDirection::Sent => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<TrafficDirection> {
+        match byte {
+            0 => Some(TrafficDirection::Received),
+            1 => Some(TrafficDirection::Sent),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedTraffic {
+    pub interface: String,
+    pub direction: TrafficDirection,
+    pub timestamp: SystemTime,
+    pub data: Vec<u8>,
+}
+
+struct RecorderState {
+    writer: BufWriter<File>,
+    names: HashMap<String, u16>,
+}
+
+// A recording file, cloned into every RecordingInterface that should write to it so one
+// session can capture several interfaces in order
+#[derive(Clone)]
+pub struct Recorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(Recorder { state: Arc::new(Mutex::new(RecorderState { writer, names: HashMap::new() })) })
+    }
+
+    pub fn record(&self, interface: &str, direction: TrafficDirection, data: &[u8]) -> io::Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        let length = u32::try_from(data.len()).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let id = match state.names.get(interface) {
+            Some(id) => *id,
+            None => {
+                let id = u16::try_from(state.names.len()).map_err(|_| io::Error::from(ErrorKind::OutOfMemory))?;
+                let name_length = u16::try_from(interface.len()).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+                state.writer.write_all(&[ENTRY_NAME])?;
+                state.writer.write_all(&id.to_le_bytes())?;
+                state.writer.write_all(&name_length.to_le_bytes())?;
+                state.writer.write_all(interface.as_bytes())?;
+                state.names.insert(interface.to_string(), id);
+                id
+            }
+        };
+        state.writer.write_all(&[ENTRY_DATA])?;
+        state.writer.write_all(&id.to_le_bytes())?;
+        state.writer.write_all(&[direction.to_byte()])?;
+        state.writer.write_all(&timestamp.to_le_bytes())?;
+        state.writer.write_all(&length.to_le_bytes())?;
+        state.writer.write_all(data)
+    }
+
+    // Entries are buffered, dropping the last Recorder clone flushes them as well
+    pub fn flush(&self) -> io::Result<()> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).writer.flush()
+    }
+}
+
+// Reads a recording back entry by entry. A recording cut short, e.g. by a crash while
+// capturing, ends at its last complete entry.
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    names: HashMap<u16, String>,
+}
+
+impl RecordingReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        RecordingReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not an interface recording"));
+        }
+        let version = u16::from_le_bytes([header[6], header[7]]);
+        if version != VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("unsupported recording version {}", version)));
+        }
+        Ok(RecordingReader { reader, names: HashMap::new() })
+    }
+
+    fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<RecordedTraffic>> {
+        loop {
+            let [kind] = match self.read_array::<1>() {
+                Ok(kind) => kind,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let id = u16::from_le_bytes(self.read_array()?);
+            match kind {
+                ENTRY_NAME => {
+                    let mut name = vec![0u8; u16::from_le_bytes(self.read_array()?) as usize];
+                    self.reader.read_exact(&mut name)?;
+                    let name = String::from_utf8(name).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                    self.names.insert(id, name);
+                }
+                ENTRY_DATA => {
+                    let [direction] = self.read_array::<1>()?;
+                    let direction = TrafficDirection::from_byte(direction)
+                        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "unknown traffic direction"))?;
+                    let timestamp = UNIX_EPOCH + Duration::from_nanos(u64::from_le_bytes(self.read_array()?));
+                    let mut data = vec![0u8; u32::from_le_bytes(self.read_array()?) as usize];
+                    self.reader.read_exact(&mut data)?;
+                    let interface = self.names.get(&id)
+                        .cloned()
+                        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "data entry of an unnamed interface"))?;
+                    return Ok(Some(RecordedTraffic { interface, direction, timestamp, data }));
+                }
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, format!("unknown entry type {}", kind))),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<RecordedTraffic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_entry() {
+            Ok(entry) => entry.map(Ok),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+// Passes everything through to the wrapped interface and records each successful read
+// and write, tagged with the interface name
+pub struct RecordingInterface<I: InterfaceTrait> {
+    inner: I,
+    recorder: Recorder,
+}
+
+impl<I: InterfaceTrait> RecordingInterface<I> {
+    pub fn new(inner: I, recorder: Recorder) -> Self {
+        RecordingInterface { inner, recorder }
+    }
+
+    pub fn get_recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    pub fn get_inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn get_inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    // A failing recording is logged and counted, but never fails the traffic itself
+    fn record(&mut self, direction: TrafficDirection, data: &[u8]) {
+        let name = self.inner.base_interface().get_name();
+        if let Err(e) = self.recorder.record(&name, direction, data) {
+            self.inner.base_interface_mut().raise_io(e);
+        }
+    }
+}
+
+impl<I: InterfaceTrait> InterfaceTrait for RecordingInterface<I> {
+    fn base_interface(&self) -> &BaseInterface {
+        self.inner.base_interface()
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        self.inner.base_interface_mut()
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.inner.poll_fds()
+    }
+
+    fn has_pending_input(&self) -> bool {
+        self.inner.has_pending_input()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout);
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout);
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.inner.set_nonblocking(nonblocking);
+    }
+
+    fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        self.inner.set_reconnect_policy(policy);
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        let result = self.inner.close();
+        if let Err(e) = self.recorder.flush() {
+            self.inner.base_interface_mut().raise_io(e);
+        }
+        result
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let bytes_read = self.inner.read(buffer)?;
+        if bytes_read > 0 {
+            self.record(TrafficDirection::Received, &buffer[..bytes_read as usize]);
+        }
+        Ok(bytes_read)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        self.inner.write(buffer)?;
+        self.record(TrafficDirection::Sent, buffer);
+        Ok(())
+    }
+}
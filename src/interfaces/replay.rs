@@ -0,0 +1,453 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::processor_base::processing::DataProcessor;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol, InterfaceStatus,
+            InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+// Frame recordings start with MAGIC and a little endian u16 version, followed by one entry
+// per frame: ifcode, id, timestamp_sec, timestamp_nsec and data_size as little endian u64,
+// then data_size bytes of data
+const MAGIC: &[u8; 6] = b"DPREC\0";
+const VERSION: u16 = 1;
+const FRAME_HEADER_SIZE: usize = 40;
+
+pub const MIN_REPLAY_SPEED: f64 = 0.1;
+pub const MAX_REPLAY_SPEED: f64 = 100.0;
+
+// Writes DataProcessor frames into a recording a ReplayInterface can play back
+pub struct FrameRecorder {
+    writer: BufWriter<File>,
+}
+
+impl FrameRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<FrameRecorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(FrameRecorder { writer })
+    }
+
+    pub fn write_frame(&mut self, frame: &DataProcessor) -> io::Result<()> {
+        for value in [frame.ifcode(), frame.id(), frame.timestamp_sec(), frame.timestamp_nsec(), frame.data().len() as u64] {
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.writer.write_all(frame.data())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn frame_time(timestamp_sec: u64, timestamp_nsec: u64) -> u128 {
+    timestamp_sec as u128 * 1_000_000_000 + timestamp_nsec as u128
+}
+
+struct ReplayClock {
+    speed: f64,
+    paused: bool,
+    as_fast_as_possible: bool,
+    looping: bool,
+    // Recording time (ns since the first frame) that was current at anchor_instant
+    anchor_time: u128,
+    anchor_instant: Instant,
+    seek_to: Option<u128>,
+}
+
+impl ReplayClock {
+    fn position(&self, now: Instant) -> u128 {
+        if self.paused {
+            return self.anchor_time;
+        }
+        let elapsed = now.saturating_duration_since(self.anchor_instant).as_nanos() as f64 * self.speed;
+        self.anchor_time + elapsed as u128
+    }
+
+    fn anchor(&mut self, time: u128, now: Instant) {
+        self.anchor_time = time;
+        self.anchor_instant = now;
+    }
+
+    // How long until the recording reaches `time`, None while paused
+    fn wait_for(&self, time: u128, now: Instant) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let remaining = time.saturating_sub(self.position(now)) as f64 / self.speed;
+        Some(Duration::from_nanos(remaining.ceil() as u64))
+    }
+}
+
+// Controls a replay from any thread, also while a read is waiting for its frame
+#[derive(Clone)]
+pub struct ReplayControl {
+    clock: Arc<(Mutex<ReplayClock>, Condvar)>,
+}
+
+impl ReplayControl {
+    fn new() -> Self {
+        let clock = ReplayClock {
+            speed: 1.0,
+            paused: false,
+            as_fast_as_possible: false,
+            looping: false,
+            anchor_time: 0,
+            anchor_instant: Instant::now(),
+            seek_to: None,
+        };
+        ReplayControl { clock: Arc::new((Mutex::new(clock), Condvar::new())) }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReplayClock> {
+        self.clock.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update<F: FnOnce(&mut ReplayClock, Instant)>(&self, change: F) {
+        change(&mut self.lock(), Instant::now());
+        self.clock.1.notify_all();
+    }
+
+    pub fn pause(&self) {
+        self.update(|clock, now| {
+            let position = clock.position(now);
+            clock.anchor(position, now);
+            clock.paused = true;
+        });
+    }
+
+    pub fn resume(&self) {
+        self.update(|clock, now| {
+            let position = clock.position(now);
+            clock.paused = false;
+            clock.anchor(position, now);
+        });
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    // Playback rate relative to the recording, from MIN_REPLAY_SPEED to MAX_REPLAY_SPEED;
+    // returns false and leaves the speed alone outside of that range
+    pub fn set_speed(&self, speed: f64) -> bool {
+        if !(MIN_REPLAY_SPEED..=MAX_REPLAY_SPEED).contains(&speed) {
+            return false;
+        }
+        self.update(|clock, now| {
+            let position = clock.position(now);
+            clock.anchor(position, now);
+            clock.speed = speed;
+        });
+        true
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.lock().speed
+    }
+
+    // Frames are released as soon as they are read, ignoring their timestamps
+    pub fn set_as_fast_as_possible(&self, as_fast_as_possible: bool) {
+        self.update(|clock, now| {
+            let position = clock.position(now);
+            clock.anchor(position, now);
+            clock.as_fast_as_possible = as_fast_as_possible;
+        });
+    }
+
+    // Starts over from the first frame after the last one instead of ending
+    pub fn set_looping(&self, looping: bool) {
+        self.update(|clock, _| clock.looping = looping);
+    }
+
+    // Continues with the first frame at or after `offset` from the start of the recording
+    pub fn seek(&self, offset: Duration) {
+        self.update(|clock, now| {
+            clock.seek_to = Some(offset.as_nanos());
+            clock.anchor(offset.as_nanos(), now);
+        });
+    }
+
+    // Replay time as offset from the start of the recording
+    pub fn get_position(&self) -> Duration {
+        let position = self.lock().position(Instant::now());
+        Duration::from_nanos(position.min(u64::MAX as u128) as u64)
+    }
+}
+
+// Plays a frame recording back, releasing each frame when the time since the first frame
+// matches its timestamp. The frames keep their original timestamps.
+pub struct ReplayInterface {
+    file_path: String,
+    reader: Option<BufReader<File>>,
+    // Recording time and file offset of every frame, built when opening
+    index: Vec<(u128, u64)>,
+    next: usize,
+    // The reader sits at the entry of `next`, no seek needed
+    in_position: bool,
+    control: ReplayControl,
+    base_interface: BaseInterface,
+}
+
+impl ReplayInterface {
+    pub fn new(name: String, description: String, file_path: String, log_if: Option<bool>) -> Self {
+        ReplayInterface {
+            file_path,
+            reader: None,
+            index: Vec::new(),
+            next: 0,
+            in_position: false,
+            control: ReplayControl::new(),
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::File},
+                                            InterfaceMode::Read,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn control(&self) -> ReplayControl {
+        self.control.clone()
+    }
+
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    pub fn set_speed(&mut self, speed: f64) -> Result<(), InterfaceError> {
+        if !self.control.set_speed(speed) {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                format!("replay speed {} is outside of {}..{}", speed, MIN_REPLAY_SPEED, MAX_REPLAY_SPEED)));
+        }
+        Ok(())
+    }
+
+    pub fn set_as_fast_as_possible(&self, as_fast_as_possible: bool) {
+        self.control.set_as_fast_as_possible(as_fast_as_possible);
+    }
+
+    pub fn set_looping(&self, looping: bool) {
+        self.control.set_looping(looping);
+    }
+
+    pub fn seek(&self, offset: Duration) {
+        self.control.seek(offset);
+    }
+
+    pub fn get_position(&self) -> Duration {
+        self.control.get_position()
+    }
+
+    // Time from the first to the last frame, zero while closed
+    pub fn get_duration(&self) -> Duration {
+        let last = self.index.last().map(|(time, _)| *time).unwrap_or(0);
+        Duration::from_nanos(last.min(u64::MAX as u128) as u64)
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.index.len()
+    }
+
+    fn build_index(reader: &mut BufReader<File>) -> io::Result<Vec<(u128, u64)>> {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..6] != MAGIC || u16::from_le_bytes([header[6], header[7]]) != VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a frame recording"));
+        }
+        let length = reader.get_ref().metadata()?.len();
+        let mut index = Vec::new();
+        let mut offset = header.len() as u64;
+        let mut start = None;
+        let mut entry = [0u8; FRAME_HEADER_SIZE];
+        // A recording cut short ends at its last complete frame
+        while offset + FRAME_HEADER_SIZE as u64 <= length {
+            reader.read_exact(&mut entry)?;
+            let field = |n: usize| u64::from_le_bytes(entry[n * 8..n * 8 + 8].try_into().unwrap());
+            let data_size = field(4);
+            // A corrupt size ends the recording just like a frame cut short
+            let end = match (offset + FRAME_HEADER_SIZE as u64).checked_add(data_size) {
+                Some(end) if end <= length => end,
+                _ => break,
+            };
+            let time = frame_time(field(2), field(3));
+            let start = *start.get_or_insert(time);
+            // Frames stamped earlier than the first one go out straight away
+            index.push((time.saturating_sub(start), offset));
+            reader.seek_relative(data_size as i64)?;
+            offset = end;
+        }
+        reader.seek(SeekFrom::Start(header.len() as u64))?;
+        Ok(index)
+    }
+
+    fn read_entry(&mut self) -> Result<DataProcessor, InterfaceError> {
+        let reader = match self.reader.as_mut() {
+            Some(reader) => reader,
+            None => return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+        };
+        let result = (|| {
+            if !self.in_position {
+                reader.seek(SeekFrom::Start(self.index[self.next].1))?;
+            }
+            let mut entry = [0u8; FRAME_HEADER_SIZE];
+            reader.read_exact(&mut entry)?;
+            let field = |n: usize| u64::from_le_bytes(entry[n * 8..n * 8 + 8].try_into().unwrap());
+            let mut data = vec![0u8; field(4) as usize];
+            reader.read_exact(&mut data)?;
+            Ok::<_, io::Error>(DataProcessor::new(field(0), field(1), field(2), field(3), field(4), data))
+        })();
+        self.next += 1;
+        self.in_position = result.is_ok();
+        result.map_err(|e| self.base_interface.raise_io(e))
+    }
+
+    // Waits, within the read timeout, until the next frame is due. None at the end of a
+    // recording that does not loop.
+    pub fn read_frame(&mut self) -> Result<Option<DataProcessor>, InterfaceError> {
+        let frame = self.next_frame()?;
+        if let Some(frame) = frame.as_ref() {
+            self.base_interface.error = None;
+            self.base_interface.data_received(frame.data().len());
+        }
+        Ok(frame)
+    }
+
+    fn next_frame(&mut self) -> Result<Option<DataProcessor>, InterfaceError> {
+        if self.reader.is_none() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        let control = self.control.clone();
+        let (lock, changed) = &*control.clock;
+        let mut clock = lock.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            if let Some(target) = clock.seek_to.take() {
+                self.next = self.index.partition_point(|(time, _)| *time < target);
+                self.in_position = false;
+            }
+            if self.next >= self.index.len() {
+                if !clock.looping || self.index.is_empty() {
+                    return Ok(None);
+                }
+                self.next = 0;
+                self.in_position = false;
+                clock.anchor(0, now);
+            }
+            let time = self.index[self.next].0;
+            // A pause holds fast playback too
+            let wait = if clock.as_fast_as_possible && !clock.paused {
+                Some(Duration::ZERO)
+            } else {
+                clock.wait_for(time, now)
+            };
+            if wait == Some(Duration::ZERO) {
+                // Late frames keep the schedule, fast frames move the clock along for a later switch back
+                if clock.as_fast_as_possible {
+                    clock.anchor(time, now);
+                }
+                drop(clock);
+                return self.read_entry().map(Some);
+            }
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(now));
+            if remaining == Some(Duration::ZERO) {
+                drop(clock);
+                return Err(self.base_interface.timed_out());
+            }
+            let timeout = match (wait, remaining) {
+                (Some(wait), Some(remaining)) => Some(wait.min(remaining)),
+                (wait, remaining) => wait.or(remaining),
+            };
+            clock = match timeout {
+                Some(timeout) => changed.wait_timeout(clock, timeout).unwrap_or_else(|e| e.into_inner()).0,
+                None => changed.wait(clock).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+impl InterfaceTrait for ReplayInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    // True once the next frame is due, so the manager can poll a replay like any other input
+    fn has_pending_input(&self) -> bool {
+        let clock = self.control.lock();
+        match self.index.get(self.next) {
+            Some((time, _)) => {
+                !clock.paused
+                    && (clock.as_fast_as_possible || clock.wait_for(*time, Instant::now()) == Some(Duration::ZERO))
+            }
+            None => clock.looping && !self.index.is_empty() && !clock.paused,
+        }
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let file = File::open(&self.file_path).map_err(|e| self.base_interface.raise_io(e))?;
+        let mut reader = BufReader::new(file);
+        self.index = ReplayInterface::build_index(&mut reader).map_err(|e| match e.kind() {
+            ErrorKind::InvalidData | ErrorKind::UnexpectedEof => {
+                self.base_interface.raise_with(InterfaceErrorKind::ProtocolError, e)
+            }
+            _ => self.base_interface.raise_io(e),
+        })?;
+        self.reader = Some(reader);
+        self.next = 0;
+        self.in_position = true;
+        self.control.update(|clock, now| {
+            clock.seek_to = None;
+            clock.anchor(0, now);
+        });
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.reader = None;
+        self.index.clear();
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    // Hands out the data of the next frame, 0 bytes at the end of the recording. A frame too
+    // big for the buffer fails with Overflow and is read again by the next read.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let frame = match self.next_frame()? {
+            Some(frame) => frame,
+            None => return Ok(0),
+        };
+        if frame.data().len() > buffer.len() {
+            self.next -= 1;
+            self.in_position = false;
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        buffer[..frame.data().len()].copy_from_slice(frame.data());
+        self.base_interface.error = None;
+        self.base_interface.data_received(frame.data().len());
+        Ok(frame.data().len() as u32)
+    }
+
+    fn write(&mut self, _buffer: &[u8]) -> Result<(), InterfaceError> {
+        Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly))
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::OpenOptionsExt;
+
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialParity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialStopBits {
+    One,
+    Two,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialFlowControl {
+    None,
+    Software,
+    Hardware,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialLine {
+    Generic,
+    RS232,
+    RS485,
+}
+
+#[derive(Clone, Debug)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub parity: SerialParity,
+    pub stop_bits: SerialStopBits,
+    pub flow_control: SerialFlowControl,
+    pub line: SerialLine,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        SerialConfig {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: SerialParity::None,
+            stop_bits: SerialStopBits::One,
+            flow_control: SerialFlowControl::None,
+            line: SerialLine::Generic,
+        }
+    }
+}
+
+// linux/serial.h, not exported by libc
+#[repr(C)]
+#[derive(Default)]
+struct SerialIcounter {
+    cts: libc::c_int,
+    dsr: libc::c_int,
+    rng: libc::c_int,
+    dcd: libc::c_int,
+    rx: libc::c_int,
+    tx: libc::c_int,
+    frame: libc::c_int,
+    overrun: libc::c_int,
+    parity: libc::c_int,
+    brk: libc::c_int,
+    buf_overrun: libc::c_int,
+    reserved: [libc::c_int; 9],
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+const SER_RS485_ENABLED: u32 = 1 << 0;
+const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+
+fn baud_rate_constant(baud_rate: u32) -> Option<libc::speed_t> {
+    let speed = match baud_rate {
+        50 => libc::B50,
+        75 => libc::B75,
+        110 => libc::B110,
+        134 => libc::B134,
+        150 => libc::B150,
+        200 => libc::B200,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        1800 => libc::B1800,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        500000 => libc::B500000,
+        576000 => libc::B576000,
+        921600 => libc::B921600,
+        1000000 => libc::B1000000,
+        1152000 => libc::B1152000,
+        1500000 => libc::B1500000,
+        2000000 => libc::B2000000,
+        2500000 => libc::B2500000,
+        3000000 => libc::B3000000,
+        3500000 => libc::B3500000,
+        4000000 => libc::B4000000,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+pub struct SerialInterface {
+    device_path: String,
+    config: SerialConfig,
+    file: Option<File>,
+    original_termios: Option<libc::termios>,
+    // Raw bytes as delivered by the tty, still carrying the PARMRK escapes
+    raw_input: VecDeque<u8>,
+    pending_error: Option<InterfaceErrorKind>,
+    error_counters: Option<SerialIcounter>,
+    base_interface: BaseInterface,
+}
+
+impl SerialInterface {
+    pub fn new(name: String, description: String, device_path: String, config: SerialConfig, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        let phys = match config.line {
+            SerialLine::Generic => PhysInterface::Serial,
+            SerialLine::RS232 => PhysInterface::RS232,
+            SerialLine::RS485 => PhysInterface::RS485,
+        };
+        SerialInterface {
+            device_path,
+            config,
+            file: None,
+            original_termios: None,
+            raw_input: VecDeque::new(),
+            pending_error: None,
+            error_counters: None,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys, logic: LogicalInterface::File},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn get_config(&self) -> SerialConfig {
+        self.config.clone()
+    }
+
+    fn configure(&mut self, fd: libc::c_int) -> Result<(), InterfaceError> {
+        let speed = match baud_rate_constant(self.config.baud_rate) {
+            Some(speed) => speed,
+            None => {
+                return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                                                           format!("Unsupported baud rate {}",
+                                                                   self.config.baud_rate)));
+            }
+        };
+        let data_bits = match self.config.data_bits {
+            5 => libc::CS5,
+            6 => libc::CS6,
+            7 => libc::CS7,
+            8 => libc::CS8,
+            _ => {
+                return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                                                           format!("Unsupported data bits {}",
+                                                                   self.config.data_bits)));
+            }
+        };
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        self.original_termios = Some(termios);
+        unsafe { libc::cfmakeraw(&mut termios) };
+        termios.c_cflag &= !(libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS);
+        termios.c_cflag |= data_bits | libc::CLOCAL | libc::CREAD;
+        termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY | libc::IGNPAR | libc::INPCK | libc::ISTRIP);
+        // Bytes received with a parity or framing error are marked in-band as 0xFF 0x00 <byte>
+        termios.c_iflag |= libc::PARMRK;
+        match self.config.parity {
+            SerialParity::None => {}
+            SerialParity::Odd => {
+                termios.c_cflag |= libc::PARENB | libc::PARODD;
+                termios.c_iflag |= libc::INPCK;
+            }
+            SerialParity::Even => {
+                termios.c_cflag |= libc::PARENB;
+                termios.c_iflag |= libc::INPCK;
+            }
+        }
+        if let SerialStopBits::Two = self.config.stop_bits {
+            termios.c_cflag |= libc::CSTOPB;
+        }
+        match self.config.flow_control {
+            SerialFlowControl::None => {}
+            SerialFlowControl::Software => termios.c_iflag |= libc::IXON | libc::IXOFF,
+            SerialFlowControl::Hardware => termios.c_cflag |= libc::CRTSCTS,
+        }
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        unsafe {
+            if libc::cfsetspeed(&mut termios, speed) != 0
+                || libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0
+                || libc::tcflush(fd, libc::TCIOFLUSH) != 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+        }
+        if let SerialLine::RS485 = self.config.line {
+            // Let the driver toggle RTS around each transmission to drive the transceiver
+            let rs485 = SerialRs485 { flags: SER_RS485_ENABLED | SER_RS485_RTS_ON_SEND, ..Default::default() };
+            if unsafe { libc::ioctl(fd, libc::TIOCSRS485, &rs485) } != 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+        }
+        self.error_counters = self.read_error_counters(fd);
+        Ok(())
+    }
+
+    // UART line status counters, unavailable on pseudo-terminals and USB adapters without support
+    fn read_error_counters(&self, fd: libc::c_int) -> Option<SerialIcounter> {
+        let mut counters = SerialIcounter::default();
+        if unsafe { libc::ioctl(fd, libc::TIOCGICOUNT, &mut counters) } == 0 {
+            Some(counters)
+        } else {
+            None
+        }
+    }
+
+    // The in-band mark does not tell parity from framing errors, the driver counters do
+    fn classify_line_error(&mut self, marked_byte: u8) -> InterfaceErrorKind {
+        let fd = match self.file.as_ref() {
+            Some(file) => file.as_raw_fd(),
+            None => return InterfaceErrorKind::GenericError,
+        };
+        if let (Some(previous), Some(current)) = (self.error_counters.take(), self.read_error_counters(fd)) {
+            let error = if current.parity > previous.parity {
+                InterfaceErrorKind::ParityError
+            } else if current.overrun > previous.overrun || current.buf_overrun > previous.buf_overrun {
+                InterfaceErrorKind::Overflow
+            } else {
+                InterfaceErrorKind::FramingError
+            };
+            self.error_counters = Some(current);
+            return error;
+        }
+        // A break is reported as a NUL byte, otherwise the parity setting is the best guess
+        match (marked_byte, self.config.parity) {
+            (0, _) | (_, SerialParity::None) => InterfaceErrorKind::FramingError,
+            _ => InterfaceErrorKind::ParityError,
+        }
+    }
+
+    // Moves clean bytes to `buffer` until it is full, the raw input runs out or a marked byte is found
+    fn decode_input(&mut self, buffer: &mut [u8]) -> (usize, Option<InterfaceErrorKind>) {
+        let mut decoded = 0;
+        while decoded < buffer.len() {
+            match self.raw_input.front() {
+                None => break,
+                Some(0xFF) => {
+                    match (self.raw_input.get(1), self.raw_input.get(2)) {
+                        (Some(0xFF), _) => {
+                            self.raw_input.drain(..2);
+                            buffer[decoded] = 0xFF;
+                            decoded += 1;
+                        }
+                        (Some(0x00), Some(marked_byte)) => {
+                            let marked_byte = *marked_byte;
+                            self.raw_input.drain(..3);
+                            return (decoded, Some(self.classify_line_error(marked_byte)));
+                        }
+                        // Escape sequence split across two reads, wait for the rest
+                        (None, _) | (Some(0x00), None) => break,
+                        (Some(_), _) => {
+                            self.raw_input.pop_front();
+                            buffer[decoded] = 0xFF;
+                            decoded += 1;
+                        }
+                    }
+                }
+                Some(byte) => {
+                    buffer[decoded] = *byte;
+                    self.raw_input.pop_front();
+                    decoded += 1;
+                }
+            }
+        }
+        (decoded, None)
+    }
+}
+
+impl InterfaceTrait for SerialInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.file.iter().map(|file| file.as_raw_fd()).collect()
+    }
+
+    // A lone 0xFF or 0xFF 0x00 may still be the start of an escape sequence
+    fn has_pending_input(&self) -> bool {
+        if self.pending_error.is_some() {
+            return true;
+        }
+        match (self.raw_input.front(), self.raw_input.get(1)) {
+            (None, _) => false,
+            (Some(0xFF), None) => false,
+            (Some(0xFF), Some(0x00)) => self.raw_input.len() > 2,
+            _ => true,
+        }
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let mut options = OpenOptions::new();
+        match self.base_interface.get_mode() {
+            InterfaceMode::Read => options.read(true),
+            InterfaceMode::Write => options.write(true),
+            InterfaceMode::ReadWrite => options.read(true).write(true),
+        };
+        let file = options
+            .custom_flags(libc::O_NOCTTY)
+            .open(&self.device_path)
+            .map_err(|e| self.base_interface.raise_io(e))?;
+        if let Err(e) = self.configure(file.as_raw_fd()) {
+            // Raw mode may already be applied, the device is handed back the way it was found
+            if let Some(termios) = self.original_termios.take() {
+                unsafe { libc::tcsetattr(file.as_raw_fd(), libc::TCSANOW, &termios) };
+            }
+            return Err(e);
+        }
+        self.file = Some(file);
+        self.raw_input.clear();
+        self.pending_error = None;
+        self.base_interface.status = InterfaceStatus::Connected;
+        self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        if let Some(file) = self.file.take() {
+            self.base_interface.status = InterfaceStatus::Disconnected;
+            if let Some(termios) = self.original_termios.take() {
+                unsafe {
+                    libc::tcdrain(file.as_raw_fd());
+                    libc::tcsetattr(file.as_raw_fd(), libc::TCSANOW, &termios);
+                }
+            }
+            Ok(())
+        } else {
+            Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        // Good bytes received before a line error are delivered first, the error on the next call
+        if let Some(error) = self.pending_error.take() {
+            return Err(self.base_interface.raise(error));
+        }
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        self.base_interface.error = None;
+        let mut chunk = vec![0u8; buffer.len().max(64)];
+        loop {
+            let (decoded, error) = self.decode_input(buffer);
+            if decoded > 0 {
+                self.pending_error = error;
+                self.base_interface.data_received(decoded);
+                return Ok(decoded as u32);
+            }
+            if let Some(error) = error {
+                return Err(self.base_interface.raise(error));
+            }
+            let file = self.file.as_mut().unwrap();
+            self.base_interface.wait_ready(file.as_raw_fd(), false)?;
+            match file.read(&mut chunk) {
+                Ok(0) => return Ok(0),
+                Ok(bytes_read) => self.raw_input.extend(&chunk[..bytes_read]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        match self.base_interface.get_status() {
+            InterfaceStatus::Connected => {
+                self.base_interface.error = None;
+                if let Some(file) = self.file.as_mut() {
+                    self.base_interface.wait_ready(file.as_raw_fd(), true)?;
+                    file.write_all(buffer).map_err(|e| self.base_interface.raise_io(e))?;
+                    self.base_interface.data_sent(buffer.len());
+                    Ok(())
+                } else {
+                    Err(self.base_interface.raise(InterfaceErrorKind::GenericError))
+                }
+            }
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::ffi::CString;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::processor_base::processing::DataProcessor;
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceMode, InterfaceProtocol,
+            InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface, PhysInterface};
+
+// Segment layout:
+//   header | slot 0 | slot 1 | ... | slot (slot_count - 1)
+// Every slot starts with a sequence word used as a seqlock: it holds 2*n+1 while
+// frame n is being written and 2*n+2 once the frame is complete. Readers keep
+// their own cursor, so any number of consumers can follow one producer without
+// ever blocking it; a consumer that falls more than slot_count frames behind
+// detects the overrun from the sequence words. Slots are only accessed through atomics,
+// as the producer may rewrite a slot while a consumer is copying it out. Each time the
+// producer creates the segment it stamps a new session, which tells attached consumers
+// that the ring restarted from sequence 0.
+const SHM_MAGIC: u64 = 0x5045_5348_4d52_4e47;
+const HEADER_SIZE: usize = 64;
+const SLOT_HEADER_SIZE: usize = 48;
+
+#[repr(C)]
+struct RingHeader {
+    magic: AtomicU64,
+    slot_count: u64,
+    slot_size: u64,
+    write_seq: AtomicU64,
+    session: AtomicU64,
+}
+
+#[repr(C)]
+struct SlotHeader {
+    sequence: AtomicU64,
+    ifcode: AtomicU64,
+    id: AtomicU64,
+    timestamp_sec: AtomicU64,
+    timestamp_nsec: AtomicU64,
+    data_size: AtomicU64,
+}
+
+fn slot_stride(slot_size: usize) -> usize {
+    SLOT_HEADER_SIZE + slot_size.div_ceil(8) * 8
+}
+
+// Payloads are copied a word at a time, slots are 8 byte aligned and padded to whole words
+unsafe fn store_words(destination: *mut u8, data: &[u8]) {
+    for (index, chunk) in data.chunks(8).enumerate() {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        unsafe { (*(destination.add(index * 8) as *const AtomicU64)).store(u64::from_ne_bytes(word), Ordering::Relaxed) };
+    }
+}
+
+unsafe fn load_words(source: *const u8, data: &mut [u8]) {
+    for (index, chunk) in data.chunks_mut(8).enumerate() {
+        let word = unsafe { (*(source.add(index * 8) as *const AtomicU64)).load(Ordering::Relaxed) };
+        chunk.copy_from_slice(&word.to_ne_bytes()[..chunk.len()]);
+    }
+}
+
+pub struct SharedMemoryInterface {
+    shm_name: String,
+    slot_count: usize,
+    slot_size: usize,
+    mapping: *mut u8,
+    mapping_len: usize,
+    read_seq: u64,
+    // Session of the segment as this consumer knows it
+    session: u64,
+    // Payload copied out of the slot before it is handed on
+    scratch: Vec<u8>,
+    lost_frames: u64,
+    unlink_on_close: bool,
+    base_interface: BaseInterface,
+}
+
+// The mapping is owned by the interface and only touched through &mut self
+unsafe impl Send for SharedMemoryInterface {}
+
+impl SharedMemoryInterface {
+    // slot_count and slot_size are only used by the producer, consumers read them from the segment
+    pub fn new(name: String, description: String, shm_name: String, slot_count: usize, slot_size: usize, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        if !shm_name.starts_with('/') || shm_name[1..].contains('/') {
+            panic!("Invalid shared memory name");
+        }
+        SharedMemoryInterface {
+            shm_name,
+            slot_count,
+            slot_size,
+            mapping: ptr::null_mut(),
+            mapping_len: 0,
+            read_seq: 0,
+            session: 0,
+            scratch: Vec::new(),
+            lost_frames: 0,
+            unlink_on_close: true,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::SharedMemory},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn set_unlink_on_close(&mut self, unlink_on_close: bool) {
+        self.unlink_on_close = unlink_on_close;
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slot_count
+    }
+
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    // Frames this consumer missed because the producer lapped it
+    pub fn lost_frames(&self) -> u64 {
+        self.lost_frames
+    }
+
+    fn is_producer(&self) -> bool {
+        !matches!(self.base_interface.get_mode(), InterfaceMode::Read)
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { &*(self.mapping as *const RingHeader) }
+    }
+
+    fn slot(&self, seq: u64) -> *mut u8 {
+        let index = (seq % self.slot_count as u64) as usize;
+        unsafe { self.mapping.add(HEADER_SIZE + index * slot_stride(self.slot_size)) }
+    }
+
+    fn create_segment(&mut self) -> Result<(), InterfaceError> {
+        if self.slot_count == 0 || self.slot_size == 0 {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::GenericError,
+                                                       "Shared memory ring needs at least one slot of non-zero size"));
+        }
+        let shm_name = CString::new(self.shm_name.as_str())
+            .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+        let mapping_len = HEADER_SIZE + self.slot_count * slot_stride(self.slot_size);
+        unsafe {
+            let fd = libc::shm_open(shm_name.as_ptr(), libc::O_CREAT | libc::O_RDWR, 0o660);
+            if fd < 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+            if libc::ftruncate(fd, mapping_len as libc::off_t) != 0 {
+                let e = std::io::Error::last_os_error();
+                libc::close(fd);
+                return Err(self.base_interface.raise_io(e));
+            }
+            let mapping = libc::mmap(ptr::null_mut(), mapping_len, libc::PROT_READ | libc::PROT_WRITE,
+                                     libc::MAP_SHARED, fd, 0);
+            libc::close(fd);
+            if mapping == libc::MAP_FAILED {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+            self.mapping = mapping as *mut u8;
+            self.mapping_len = mapping_len;
+            // Start from a clean ring, the magic is published last so consumers never see a half-made header
+            ptr::write_bytes(self.mapping, 0, mapping_len);
+            let header = self.mapping as *mut RingHeader;
+            (*header).slot_count = self.slot_count as u64;
+            (*header).slot_size = self.slot_size as u64;
+            let session = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_nanos() as u64).unwrap_or(0);
+            (*header).session.store(session.max(1), Ordering::Relaxed);
+            (*header).magic.store(SHM_MAGIC, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn attach_segment(&mut self) -> Result<(), InterfaceError> {
+        let shm_name = CString::new(self.shm_name.as_str())
+            .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+        unsafe {
+            let fd = libc::shm_open(shm_name.as_ptr(), libc::O_RDONLY, 0);
+            if fd < 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+            let mut stat: libc::stat = std::mem::zeroed();
+            if libc::fstat(fd, &mut stat) != 0 {
+                let e = std::io::Error::last_os_error();
+                libc::close(fd);
+                return Err(self.base_interface.raise_io(e));
+            }
+            let mapping_len = stat.st_size as usize;
+            if mapping_len < HEADER_SIZE {
+                libc::close(fd);
+                return Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError));
+            }
+            let mapping = libc::mmap(ptr::null_mut(), mapping_len, libc::PROT_READ, libc::MAP_SHARED, fd, 0);
+            libc::close(fd);
+            if mapping == libc::MAP_FAILED {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+            self.mapping = mapping as *mut u8;
+            self.mapping_len = mapping_len;
+        }
+        let header = self.header();
+        let magic = header.magic.load(Ordering::Acquire);
+        let slot_count = header.slot_count as usize;
+        let slot_size = header.slot_size as usize;
+        let session = header.session.load(Ordering::Relaxed);
+        if magic != SHM_MAGIC
+            || slot_count == 0
+            || HEADER_SIZE + slot_count * slot_stride(slot_size) > self.mapping_len {
+            self.unmap();
+            return Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError));
+        }
+        self.slot_count = slot_count;
+        self.slot_size = slot_size;
+        self.session = session;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.mapping.is_null() {
+            unsafe { libc::munmap(self.mapping as *mut libc::c_void, self.mapping_len) };
+            self.mapping = ptr::null_mut();
+            self.mapping_len = 0;
+        }
+    }
+
+    pub fn write_frame(&mut self, frame: &DataProcessor) -> Result<(), InterfaceError> {
+        if !self.is_producer() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        if self.mapping.is_null() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        let data = frame.data();
+        if data.len() > self.slot_size {
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        let header = self.header();
+        let seq = header.write_seq.load(Ordering::Relaxed);
+        let slot = self.slot(seq);
+        unsafe {
+            let slot_header = slot as *mut SlotHeader;
+            (*slot_header).sequence.store(seq * 2 + 1, Ordering::Relaxed);
+            fence(Ordering::Release);
+            (*slot_header).ifcode.store(frame.ifcode(), Ordering::Relaxed);
+            (*slot_header).id.store(frame.id(), Ordering::Relaxed);
+            (*slot_header).timestamp_sec.store(frame.timestamp_sec(), Ordering::Relaxed);
+            (*slot_header).timestamp_nsec.store(frame.timestamp_nsec(), Ordering::Relaxed);
+            (*slot_header).data_size.store(data.len() as u64, Ordering::Relaxed);
+            store_words(slot.add(SLOT_HEADER_SIZE), data);
+            (*slot_header).sequence.store(seq * 2 + 2, Ordering::Release);
+        }
+        header.write_seq.store(seq + 1, Ordering::Release);
+        self.base_interface.error = None;
+        self.base_interface.data_sent(data.len());
+        Ok(())
+    }
+
+    // Follows a producer that re-created the segment: the new ring starts over at sequence 0.
+    // Ok(false) while the segment is being re-created, ProtocolError when its geometry changed
+    // and the consumer has to be opened again.
+    fn check_session(&mut self) -> Result<bool, InterfaceError> {
+        let header = self.header();
+        if header.magic.load(Ordering::Acquire) != SHM_MAGIC {
+            return Ok(false);
+        }
+        let session = header.session.load(Ordering::Relaxed);
+        if session == self.session {
+            return Ok(true);
+        }
+        if header.slot_count as usize != self.slot_count || header.slot_size as usize != self.slot_size {
+            return Err(self.base_interface.raise_with(InterfaceErrorKind::ProtocolError,
+                                                       "Shared memory ring was re-created with a different geometry"));
+        }
+        self.session = session;
+        self.read_seq = 0;
+        Ok(true)
+    }
+
+    // Copies the next frame out of the ring and hands it to `consume`. Ok(None) means no new
+    // frame is available yet, Overflow that the producer lapped this consumer.
+    fn poll_frame<F, R>(&mut self, consume: F) -> Result<Option<R>, InterfaceError>
+    where
+        F: FnOnce(&DataProcessor, &[u8]) -> R,
+    {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        if self.mapping.is_null() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        if !self.is_producer() && !self.check_session()? {
+            return Ok(None);
+        }
+        let write_seq = self.header().write_seq.load(Ordering::Acquire);
+        if self.read_seq >= write_seq {
+            return Ok(None);
+        }
+        if write_seq - self.read_seq > self.slot_count as u64 {
+            return Err(self.overrun(write_seq));
+        }
+        let slot = self.slot(self.read_seq);
+        let expected = self.read_seq * 2 + 2;
+        let frame = unsafe {
+            let slot_header = slot as *const SlotHeader;
+            if (*slot_header).sequence.load(Ordering::Acquire) != expected {
+                return Err(self.overrun(write_seq));
+            }
+            let data_size = ((*slot_header).data_size.load(Ordering::Relaxed) as usize).min(self.slot_size);
+            let frame = DataProcessor::new((*slot_header).ifcode.load(Ordering::Relaxed),
+                                           (*slot_header).id.load(Ordering::Relaxed),
+                                           (*slot_header).timestamp_sec.load(Ordering::Relaxed),
+                                           (*slot_header).timestamp_nsec.load(Ordering::Relaxed),
+                                           data_size as u64,
+                                           Vec::new());
+            self.scratch.resize(data_size, 0);
+            load_words(slot.add(SLOT_HEADER_SIZE), &mut self.scratch);
+            // The copy only counts if the producer did not start rewriting the slot meanwhile
+            fence(Ordering::Acquire);
+            if (*slot_header).sequence.load(Ordering::Relaxed) != expected {
+                return Err(self.overrun(self.header().write_seq.load(Ordering::Acquire)));
+            }
+            frame
+        };
+        let data_size = self.scratch.len();
+        let result = consume(&frame, &self.scratch);
+        self.read_seq += 1;
+        self.base_interface.error = None;
+        self.base_interface.data_received(data_size);
+        Ok(Some(result))
+    }
+
+    // Lends the frame to `consume` without allocating, Underflow when the producer has nothing new
+    pub fn try_read_frame_in_place<F, R>(&mut self, consume: F) -> Result<R, InterfaceError>
+    where
+        F: FnOnce(&DataProcessor, &[u8]) -> R,
+    {
+        match self.poll_frame(consume)? {
+            Some(result) => Ok(result),
+            None => {
+                Err(self.base_interface.raise(InterfaceErrorKind::Underflow))
+            }
+        }
+    }
+
+    fn copy_frame(frame: &DataProcessor, payload: &[u8]) -> DataProcessor {
+        DataProcessor::new(frame.ifcode(), frame.id(), frame.timestamp_sec(), frame.timestamp_nsec(),
+                           payload.len() as u64, payload.to_vec())
+    }
+
+    pub fn try_read_frame(&mut self) -> Result<DataProcessor, InterfaceError> {
+        self.try_read_frame_in_place(Self::copy_frame)
+    }
+
+    // Waits, within the read timeout, until the producer publishes the next frame
+    pub fn read_frame(&mut self) -> Result<DataProcessor, InterfaceError> {
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        let mut idle_rounds: u32 = 0;
+        loop {
+            if let Some(frame) = self.poll_frame(Self::copy_frame)? {
+                return Ok(frame);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(self.base_interface.timed_out());
+            }
+            Self::wait_for_producer(&mut idle_rounds);
+        }
+    }
+
+    fn wait_for_producer(idle_rounds: &mut u32) {
+        *idle_rounds = idle_rounds.saturating_add(1);
+        if *idle_rounds < 64 {
+            std::hint::spin_loop();
+        } else if *idle_rounds < 128 {
+            thread::yield_now();
+        } else {
+            thread::sleep(Duration::from_micros(50));
+        }
+    }
+
+    // Skips to the oldest frame still in the ring and reports the gap
+    fn overrun(&mut self, write_seq: u64) -> InterfaceError {
+        let resume_seq = write_seq.saturating_sub(self.slot_count as u64) + 1;
+        self.lost_frames += resume_seq.saturating_sub(self.read_seq);
+        self.read_seq = resume_seq.max(self.read_seq + 1);
+        self.base_interface.raise(InterfaceErrorKind::Overflow)
+    }
+}
+
+impl InterfaceTrait for SharedMemoryInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    // There is no descriptor to poll, the manager checks the ring directly instead
+    fn has_pending_input(&self) -> bool {
+        if self.mapping.is_null() || self.base_interface.get_mode() == InterfaceMode::Write {
+            return false;
+        }
+        let header = self.header();
+        header.session.load(Ordering::Relaxed) != self.session || header.write_seq.load(Ordering::Acquire) > self.read_seq
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        if self.is_producer() {
+            self.create_segment()?;
+        } else {
+            self.attach_segment()?;
+        }
+        // Consumers only see frames published after they attached
+        self.read_seq = self.header().write_seq.load(Ordering::Acquire);
+        self.lost_frames = 0;
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.unmap();
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        if self.is_producer() && self.unlink_on_close {
+            let shm_name = CString::new(self.shm_name.as_str())
+                .map_err(|e| self.base_interface.raise_with(InterfaceErrorKind::GenericError, e))?;
+            if unsafe { libc::shm_unlink(shm_name.as_ptr()) } != 0 {
+                return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        let mut idle_rounds: u32 = 0;
+        loop {
+            let result = self.poll_frame(|_, payload| {
+                if payload.len() > buffer.len() {
+                    return None;
+                }
+                buffer[..payload.len()].copy_from_slice(payload);
+                Some(payload.len() as u32)
+            })?;
+            match result {
+                Some(Some(bytes_read)) => return Ok(bytes_read),
+                // The frame does not fit in the caller's buffer and is dropped
+                Some(None) => {
+                    return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+                }
+                None if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                    return Err(self.base_interface.timed_out());
+                }
+                None => Self::wait_for_producer(&mut idle_rounds),
+            }
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seq = if self.mapping.is_null() { 0 } else { self.header().write_seq.load(Ordering::Relaxed) };
+        let frame = DataProcessor::new(0, seq, now.as_secs(), now.subsec_nanos() as u64,
+                                       buffer.len() as u64, buffer.to_vec());
+        self.write_frame(&frame)
+    }
+}
+
+impl Drop for SharedMemoryInterface {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
@@ -0,0 +1,335 @@
+use std::io::ErrorKind;
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::time::Instant;
+
+use super::{poll_ready, BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface};
+
+// Write end of the self-pipe the signal handler reports to, -1 while no SignalInterface is open.
+// Signal dispositions are process wide, so only one SignalInterface can be open at a time.
+static SIGNAL_PIPE_WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+// Handler calls in progress, the write end is only closed once none of them can still use it
+static SIGNAL_HANDLERS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnixSignal {
+    Interrupt,
+    Terminate,
+    Hangup,
+    User1,
+    User2,
+}
+
+impl UnixSignal {
+    pub fn number(&self) -> i32 {
+        match self {
+            UnixSignal::Interrupt => libc::SIGINT,
+            UnixSignal::Terminate => libc::SIGTERM,
+            UnixSignal::Hangup => libc::SIGHUP,
+            UnixSignal::User1 => libc::SIGUSR1,
+            UnixSignal::User2 => libc::SIGUSR2,
+        }
+    }
+
+    pub fn from_number(number: i32) -> Option<UnixSignal> {
+        match number {
+            libc::SIGINT => Some(UnixSignal::Interrupt),
+            libc::SIGTERM => Some(UnixSignal::Terminate),
+            libc::SIGHUP => Some(UnixSignal::Hangup),
+            libc::SIGUSR1 => Some(UnixSignal::User1),
+            libc::SIGUSR2 => Some(UnixSignal::User2),
+            _ => None,
+        }
+    }
+
+    // Conventional meaning of each signal for a long running processing sequence
+    pub fn action(&self) -> SignalAction {
+        match self {
+            UnixSignal::Interrupt | UnixSignal::Terminate => SignalAction::Stop,
+            UnixSignal::Hangup => SignalAction::ReloadParameters,
+            UnixSignal::User1 => SignalAction::DumpStatistics,
+            UnixSignal::User2 => SignalAction::User,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalAction {
+    Stop,
+    ReloadParameters,
+    DumpStatistics,
+    User,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SignalRecord {
+    pub signal: UnixSignal,
+    pub timestamp_sec: u64,
+    pub timestamp_nsec: u64,
+}
+
+impl SignalRecord {
+    // signal number (i32), nanoseconds (u32), seconds (u64), all little endian
+    pub const SIZE: usize = 16;
+
+    pub fn to_bytes(&self) -> [u8; SignalRecord::SIZE] {
+        let mut bytes = [0u8; SignalRecord::SIZE];
+        bytes[0..4].copy_from_slice(&self.signal.number().to_le_bytes());
+        bytes[4..8].copy_from_slice(&(self.timestamp_nsec as u32).to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.timestamp_sec.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<SignalRecord> {
+        if bytes.len() < SignalRecord::SIZE {
+            return None;
+        }
+        let signal = UnixSignal::from_number(i32::from_le_bytes(bytes[0..4].try_into().unwrap()))?;
+        Some(SignalRecord {
+            signal,
+            timestamp_nsec: u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as u64,
+            timestamp_sec: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        })
+    }
+}
+
+// Only async-signal-safe calls in here: clock_gettime and write
+extern "C" fn signal_handler(signal_number: libc::c_int) {
+    SIGNAL_HANDLERS_RUNNING.fetch_add(1, Ordering::SeqCst);
+    let fd = SIGNAL_PIPE_WRITE_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        report_signal(fd, signal_number);
+    }
+    SIGNAL_HANDLERS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn report_signal(fd: libc::c_int, signal_number: libc::c_int) {
+    unsafe {
+        let saved_errno = *libc::__errno_location();
+        let mut now: libc::timespec = std::mem::zeroed();
+        libc::clock_gettime(libc::CLOCK_REALTIME, &mut now);
+        let mut bytes = [0u8; SignalRecord::SIZE];
+        bytes[0..4].copy_from_slice(&signal_number.to_le_bytes());
+        bytes[4..8].copy_from_slice(&(now.tv_nsec as u32).to_le_bytes());
+        bytes[8..16].copy_from_slice(&(now.tv_sec as u64).to_le_bytes());
+        // The pipe is non-blocking: when the reader falls that far behind the signal is dropped
+        libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len());
+        *libc::__errno_location() = saved_errno;
+    }
+}
+
+pub struct SignalInterface {
+    signals: Vec<UnixSignal>,
+    pipe_fds: Option<(libc::c_int, libc::c_int)>,
+    previous_actions: Vec<(UnixSignal, libc::sigaction)>,
+    last_record: Option<SignalRecord>,
+    base_interface: BaseInterface,
+}
+
+impl SignalInterface {
+    pub fn new(name: String, description: String, signals: Vec<UnixSignal>, log_if: Option<bool>) -> Self {
+        SignalInterface {
+            signals,
+            pipe_fds: None,
+            previous_actions: Vec::new(),
+            last_record: None,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::Signal},
+                                            InterfaceMode::Read,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    pub fn last_record(&self) -> Option<SignalRecord> {
+        self.last_record
+    }
+
+    fn read_fd(&mut self) -> Result<libc::c_int, InterfaceError> {
+        match (self.base_interface.get_status(), self.pipe_fds) {
+            (InterfaceStatus::Connected, Some((read_fd, _))) => Ok(read_fd),
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+
+    // Returns None straight away when no signal is pending
+    pub fn try_read_signal(&mut self) -> Result<Option<SignalRecord>, InterfaceError> {
+        let record = self.take_signal()?;
+        if record.is_some() {
+            self.base_interface.data_received(SignalRecord::SIZE);
+        }
+        Ok(record)
+    }
+
+    // Waits, within the read timeout, until one of the registered signals is delivered
+    pub fn read_signal(&mut self) -> Result<SignalRecord, InterfaceError> {
+        let record = self.wait_signal()?;
+        self.base_interface.data_received(SignalRecord::SIZE);
+        Ok(record)
+    }
+
+    // try_read_signal and read_signal without the statistics, read counts a whole batch at once
+    fn take_signal(&mut self) -> Result<Option<SignalRecord>, InterfaceError> {
+        let read_fd = self.read_fd()?;
+        let mut bytes = [0u8; SignalRecord::SIZE];
+        loop {
+            let ret = unsafe { libc::read(read_fd, bytes.as_mut_ptr() as *mut libc::c_void, bytes.len()) };
+            if ret == SignalRecord::SIZE as isize {
+                break;
+            }
+            if ret >= 0 {
+                return Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError));
+            }
+            let e = std::io::Error::last_os_error();
+            match e.kind() {
+                ErrorKind::WouldBlock => return Ok(None),
+                ErrorKind::Interrupted => {}
+                _ => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+        match SignalRecord::from_bytes(&bytes) {
+            Some(record) => {
+                self.base_interface.error = None;
+                self.last_record = Some(record);
+                self.base_interface.set_event(InterfaceEvent::SignalReceived(record.signal.number()));
+                Ok(Some(record))
+            }
+            None => {
+                Err(self.base_interface.raise(InterfaceErrorKind::ProtocolError))
+            }
+        }
+    }
+
+    fn wait_signal(&mut self) -> Result<SignalRecord, InterfaceError> {
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(record) = self.take_signal()? {
+                return Ok(record);
+            }
+            let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            match poll_ready(self.read_fd()?, libc::POLLIN, timeout) {
+                Ok(true) => {}
+                Ok(false) => return Err(self.base_interface.timed_out()),
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+    }
+
+    fn restore_handlers(&mut self) {
+        for (signal, previous_action) in self.previous_actions.drain(..) {
+            unsafe { libc::sigaction(signal.number(), &previous_action, std::ptr::null_mut()) };
+        }
+    }
+
+    // The handlers are restored first so no new call picks up the write end, then it is only
+    // closed once the calls that already loaded it are done: a late write must not land in
+    // whatever reuses the descriptor
+    fn release_pipe(&mut self) {
+        self.restore_handlers();
+        if let Some((read_fd, write_fd)) = self.pipe_fds.take() {
+            let _ = SIGNAL_PIPE_WRITE_FD.compare_exchange(write_fd, -1, Ordering::SeqCst, Ordering::SeqCst);
+            while SIGNAL_HANDLERS_RUNNING.load(Ordering::SeqCst) > 0 {
+                std::thread::yield_now();
+            }
+            unsafe {
+                libc::close(read_fd);
+                libc::close(write_fd);
+            }
+        }
+    }
+}
+
+impl InterfaceTrait for SignalInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.pipe_fds.iter().map(|(read_fd, _)| *read_fd).collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } != 0 {
+            return Err(self.base_interface.raise_io(std::io::Error::last_os_error()));
+        }
+        if SIGNAL_PIPE_WRITE_FD.compare_exchange(-1, fds[1], Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            unsafe {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+            }
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        self.pipe_fds = Some((fds[0], fds[1]));
+        for signal in self.signals.clone() {
+            let mut previous_action: libc::sigaction = unsafe { std::mem::zeroed() };
+            let ret = unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = signal_handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                libc::sigaction(signal.number(), &action, &mut previous_action)
+            };
+            if ret != 0 {
+                let e = std::io::Error::last_os_error();
+                self.release_pipe();
+                return Err(self.base_interface.raise_io(e));
+            }
+            self.previous_actions.push((signal, previous_action));
+        }
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.release_pipe();
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    // Fills the buffer with as many whole SignalRecords as are pending, blocking for the first one
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if buffer.len() < SignalRecord::SIZE {
+            return Err(self.base_interface.raise(InterfaceErrorKind::Overflow));
+        }
+        let record = self.wait_signal()?;
+        buffer[..SignalRecord::SIZE].copy_from_slice(&record.to_bytes());
+        let mut bytes_read = SignalRecord::SIZE;
+        while buffer.len() - bytes_read >= SignalRecord::SIZE {
+            match self.take_signal()? {
+                Some(record) => {
+                    buffer[bytes_read..bytes_read + SignalRecord::SIZE].copy_from_slice(&record.to_bytes());
+                    bytes_read += SignalRecord::SIZE;
+                }
+                None => break,
+            }
+        }
+        self.base_interface.data_received(bytes_read);
+        Ok(bytes_read as u32)
+    }
+
+    fn write(&mut self, _buffer: &[u8]) -> Result<(), InterfaceError> {
+        Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly))
+    }
+}
+
+impl Drop for SignalInterface {
+    fn drop(&mut self) {
+        self.release_pipe();
+    }
+}
@@ -0,0 +1,191 @@
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(super) fn raw_socket_addr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let length = match addr {
+        SocketAddr::V4(addr) => {
+            let raw = libc::sockaddr_in {
+                sin_family: libc::AF_INET as libc::sa_family_t,
+                sin_port: addr.port().to_be(),
+                sin_addr: libc::in_addr { s_addr: u32::from_ne_bytes(addr.ip().octets()) },
+                sin_zero: [0; 8],
+            };
+            unsafe { ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in, raw) };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let raw = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as libc::sa_family_t,
+                sin6_port: addr.port().to_be(),
+                sin6_flowinfo: addr.flowinfo(),
+                sin6_addr: libc::in6_addr { s6_addr: addr.ip().octets() },
+                sin6_scope_id: addr.scope_id(),
+            };
+            unsafe { ptr::write(&mut storage as *mut _ as *mut libc::sockaddr_in6, raw) };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, length as libc::socklen_t)
+}
+
+pub(super) fn set_option<T>(fd: RawFd, level: libc::c_int, option: libc::c_int, value: &T) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(fd, level, option, value as *const T as *const libc::c_void,
+                         mem::size_of::<T>() as libc::socklen_t)
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Reuse options only count when they are set before bind, which UdpSocket::bind does not allow
+pub(super) fn bind_udp(local_addr: &SocketAddr, reuse_address: bool, reuse_port: bool,
+                       receive_buffer_size: Option<usize>) -> io::Result<UdpSocket> {
+    let domain = if local_addr.is_ipv4() { libc::AF_INET } else { libc::AF_INET6 };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let socket = UdpSocket::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    if reuse_address {
+        set_option(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, &(1 as libc::c_int))?;
+    }
+    if reuse_port {
+        set_option(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT, &(1 as libc::c_int))?;
+    }
+    if let Some(size) = receive_buffer_size {
+        set_receive_buffer_size(&socket, size)?;
+    }
+    let (storage, length) = raw_socket_addr(local_addr);
+    if unsafe { libc::bind(fd, &storage as *const _ as *const libc::sockaddr, length) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+pub(super) fn set_receive_buffer_size(socket: &UdpSocket, size: usize) -> io::Result<()> {
+    let size = size.min(libc::c_int::MAX as usize) as libc::c_int;
+    set_option(socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF, &size)
+}
+
+// The kernel doubles the requested size for its own bookkeeping and reports the doubled value
+pub(super) fn receive_buffer_size(socket: &UdpSocket) -> io::Result<usize> {
+    let mut size: libc::c_int = 0;
+    let mut length = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+                         &mut size as *mut _ as *mut libc::c_void, &mut length)
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(size as usize)
+}
+
+pub(super) fn interface_index(if_name: &str) -> io::Result<u32> {
+    let name = CString::new(if_name).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+// Joins or leaves `group` on the interface with index `if_index`, 0 lets the kernel pick.
+// With a source only datagrams sent by it are delivered (source-specific multicast).
+pub(super) fn multicast_membership(socket: &UdpSocket, join: bool, group: IpAddr, source: Option<IpAddr>,
+                                   if_index: u32) -> io::Result<()> {
+    let level = if group.is_ipv4() { libc::IPPROTO_IP } else { libc::IPPROTO_IPV6 };
+    let (gr_group, _) = raw_socket_addr(&SocketAddr::new(group, 0));
+    match source {
+        Some(source) => {
+            let (gsr_source, _) = raw_socket_addr(&SocketAddr::new(source, 0));
+            let request = libc::group_source_req { gsr_interface: if_index, gsr_group: gr_group, gsr_source };
+            let option = if join { libc::MCAST_JOIN_SOURCE_GROUP } else { libc::MCAST_LEAVE_SOURCE_GROUP };
+            set_option(socket.as_raw_fd(), level, option, &request)
+        }
+        None => {
+            let request = libc::group_req { gr_interface: if_index, gr_group };
+            let option = if join { libc::MCAST_JOIN_GROUP } else { libc::MCAST_LEAVE_GROUP };
+            set_option(socket.as_raw_fd(), level, option, &request)
+        }
+    }
+}
+
+// Outgoing multicast datagrams leave through the interface with index `if_index`
+pub(super) fn set_multicast_interface(socket: &UdpSocket, ipv4: bool, if_index: u32) -> io::Result<()> {
+    if ipv4 {
+        let request = libc::ip_mreqn {
+            imr_multiaddr: libc::in_addr { s_addr: 0 },
+            imr_address: libc::in_addr { s_addr: 0 },
+            imr_ifindex: if_index as libc::c_int,
+        };
+        set_option(socket.as_raw_fd(), libc::IPPROTO_IP, libc::IP_MULTICAST_IF, &request)
+    } else {
+        set_option(socket.as_raw_fd(), libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_IF, &(if_index as libc::c_int))
+    }
+}
+
+pub(super) fn set_hop_limit_v6(socket: &UdpSocket, multicast: bool, hops: u32) -> io::Result<()> {
+    let option = if multicast { libc::IPV6_MULTICAST_HOPS } else { libc::IPV6_UNICAST_HOPS };
+    set_option(socket.as_raw_fd(), libc::IPPROTO_IPV6, option, &(hops as libc::c_int))
+}
+
+pub(super) fn socket_addr_from_raw(storage: &libc::sockaddr_storage) -> Option<SocketAddr> {
+    match storage.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let raw = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(raw.sin_addr.s_addr.to_ne_bytes());
+            Some(SocketAddr::V4(SocketAddrV4::new(ip, u16::from_be(raw.sin_port))))
+        }
+        libc::AF_INET6 => {
+            let raw = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(raw.sin6_addr.s6_addr);
+            Some(SocketAddr::V6(SocketAddrV6::new(ip, u16::from_be(raw.sin6_port), raw.sin6_flowinfo, raw.sin6_scope_id)))
+        }
+        _ => None,
+    }
+}
+
+// Asks the kernel to stamp every datagram with its arrival time, read back by recv_timestamped
+pub(super) fn enable_timestamps(socket: &UdpSocket) -> io::Result<()> {
+    set_option(socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, &(1 as libc::c_int))
+}
+
+// recvfrom that also returns the kernel receive timestamp, None when the socket has none
+pub(super) fn recv_timestamped(socket: &UdpSocket, buffer: &mut [u8])
+                               -> io::Result<(usize, SocketAddr, Option<SystemTime>)> {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec { iov_base: buffer.as_mut_ptr() as *mut libc::c_void, iov_len: buffer.len() };
+    // Room for a few control messages, u64 keeps it aligned for cmsghdr
+    let mut control = [0u64; 16];
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_name = &mut storage as *mut _ as *mut libc::c_void;
+    message.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    message.msg_controllen = mem::size_of_val(&control);
+    let received = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, 0) };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let source = socket_addr_from_raw(&storage).ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+    let mut timestamp = None;
+    let mut header = unsafe { libc::CMSG_FIRSTHDR(&message) };
+    while !header.is_null() {
+        let cmsg = unsafe { &*header };
+        if cmsg.cmsg_level == libc::SOL_SOCKET && cmsg.cmsg_type == libc::SCM_TIMESTAMPNS {
+            let time = unsafe { ptr::read_unaligned(libc::CMSG_DATA(header) as *const libc::timespec) };
+            timestamp = Some(UNIX_EPOCH + Duration::new(time.tv_sec as u64, time.tv_nsec as u32));
+        }
+        header = unsafe { libc::CMSG_NXTHDR(&message, header) };
+    }
+    Ok((received as usize, source, timestamp))
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::InterfaceErrorKind;
+use super::fault::FaultKind;
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+// The throughput window is kept as this many buckets, so memory does not grow with the rate
+const WINDOW_BUCKETS: u32 = 20;
+
+#[derive(Clone, Debug)]
+struct Bucket {
+    start: Instant,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+// Traffic counters kept by every interface, see BaseInterface::get_stats
+#[derive(Clone, Debug)]
+pub struct InterfaceStats {
+    bytes_read: u64,
+    bytes_written: u64,
+    messages_read: u64,
+    messages_written: u64,
+    errors: HashMap<InterfaceErrorKind, u64>,
+    faults: HashMap<FaultKind, u64>,
+    connections_lost: u64,
+    reconnects: u64,
+    last_read: Option<SystemTime>,
+    last_write: Option<SystemTime>,
+    since: SystemTime,
+    window: Duration,
+    buckets: VecDeque<Bucket>,
+}
+
+impl InterfaceStats {
+    pub(super) fn new() -> Self {
+        InterfaceStats {
+            bytes_read: 0,
+            bytes_written: 0,
+            messages_read: 0,
+            messages_written: 0,
+            errors: HashMap::new(),
+            faults: HashMap::new(),
+            connections_lost: 0,
+            reconnects: 0,
+            last_read: None,
+            last_write: None,
+            since: SystemTime::now(),
+            window: DEFAULT_WINDOW,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    pub fn get_bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+    pub fn get_bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+    // One message per successful read or write call
+    pub fn get_messages_read(&self) -> u64 {
+        self.messages_read
+    }
+    pub fn get_messages_written(&self) -> u64 {
+        self.messages_written
+    }
+    pub fn get_error_count(&self, kind: InterfaceErrorKind) -> u64 {
+        self.errors.get(&kind).copied().unwrap_or(0)
+    }
+    pub fn get_error_counts(&self) -> &HashMap<InterfaceErrorKind, u64> {
+        &self.errors
+    }
+    pub fn get_total_errors(&self) -> u64 {
+        self.errors.values().sum()
+    }
+    // Faults injected by a FaultInterface wrapping the interface
+    pub fn get_fault_count(&self, kind: FaultKind) -> u64 {
+        self.faults.get(&kind).copied().unwrap_or(0)
+    }
+    pub fn get_fault_counts(&self) -> &HashMap<FaultKind, u64> {
+        &self.faults
+    }
+    pub fn get_total_faults(&self) -> u64 {
+        self.faults.values().sum()
+    }
+    pub fn get_connections_lost(&self) -> u64 {
+        self.connections_lost
+    }
+    pub fn get_reconnects(&self) -> u64 {
+        self.reconnects
+    }
+    pub fn get_last_read(&self) -> Option<SystemTime> {
+        self.last_read
+    }
+    pub fn get_last_write(&self) -> Option<SystemTime> {
+        self.last_write
+    }
+    // Last successful read or write, None while the interface has not moved any data
+    pub fn get_last_activity(&self) -> Option<SystemTime> {
+        self.last_read.max(self.last_write)
+    }
+    // When counting started: interface creation or the last reset
+    pub fn get_since(&self) -> SystemTime {
+        self.since
+    }
+    pub fn get_window(&self) -> Duration {
+        self.window
+    }
+    // Bytes per second read over the throughput window
+    pub fn get_read_throughput(&self) -> f64 {
+        self.throughput(|bucket| bucket.bytes_read)
+    }
+    pub fn get_write_throughput(&self) -> f64 {
+        self.throughput(|bucket| bucket.bytes_written)
+    }
+
+    pub(super) fn set_window(&mut self, window: Duration) {
+        self.window = window.max(Duration::from_millis(1));
+        self.buckets.clear();
+    }
+
+    pub(super) fn reset(&mut self) {
+        let window = self.window;
+        *self = InterfaceStats::new();
+        self.window = window;
+    }
+
+    pub(super) fn record_read(&mut self, bytes: usize) {
+        self.bytes_read += bytes as u64;
+        self.messages_read += 1;
+        self.last_read = Some(SystemTime::now());
+        self.bucket().bytes_read += bytes as u64;
+    }
+
+    pub(super) fn record_write(&mut self, bytes: usize) {
+        self.bytes_written += bytes as u64;
+        self.messages_written += 1;
+        self.last_write = Some(SystemTime::now());
+        self.bucket().bytes_written += bytes as u64;
+    }
+
+    pub(super) fn record_error(&mut self, kind: InterfaceErrorKind) {
+        *self.errors.entry(kind).or_insert(0) += 1;
+    }
+
+    pub(super) fn record_fault(&mut self, kind: FaultKind) {
+        *self.faults.entry(kind).or_insert(0) += 1;
+    }
+
+    pub(super) fn record_connection_lost(&mut self) {
+        self.connections_lost += 1;
+    }
+
+    pub(super) fn record_reconnect(&mut self) {
+        self.reconnects += 1;
+    }
+
+    fn bucket_length(&self) -> Duration {
+        self.window / WINDOW_BUCKETS
+    }
+
+    // Bucket covering the current instant, older ones that left the window are dropped
+    fn bucket(&mut self) -> &mut Bucket {
+        let now = Instant::now();
+        while self.buckets.front().is_some_and(|bucket| now.duration_since(bucket.start) > self.window) {
+            self.buckets.pop_front();
+        }
+        let current = self.buckets.back().is_some_and(|bucket| now.duration_since(bucket.start) < self.bucket_length());
+        if !current {
+            self.buckets.push_back(Bucket { start: now, bytes_read: 0, bytes_written: 0 });
+        }
+        self.buckets.back_mut().unwrap()
+    }
+
+    fn throughput(&self, bytes: impl Fn(&Bucket) -> u64) -> f64 {
+        let now = Instant::now();
+        let total: u64 = self.buckets.iter()
+            .filter(|bucket| now.duration_since(bucket.start) <= self.window)
+            .map(bytes)
+            .sum();
+        total as f64 / self.window.as_secs_f64()
+    }
+}
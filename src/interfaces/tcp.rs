@@ -0,0 +1,640 @@
+use std::future;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use super::async_interface::{AsyncInterfaceTrait, InterfaceFuture};
+use super::reactor::{deregister, poll_io};
+use super::reconnect::recover;
+use super::socket::raw_socket_addr;
+use super::{poll_ready, BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface, ReconnectPolicy};
+
+fn is_connection_lost(kind: ErrorKind) -> bool {
+    InterfaceErrorKind::from_io(kind) == InterfaceErrorKind::ConnectionLost
+}
+
+// Waits until `fd` is ready or `deadline` has passed, None waits for as long as it takes
+fn ready_before(fd: RawFd, events: libc::c_short, deadline: Option<Instant>) -> io::Result<bool> {
+    poll_ready(fd, events, deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())))
+}
+
+// Writes as much of `data` as the non-blocking stream takes before `deadline`
+fn write_before(stream: &mut TcpStream, data: &[u8], deadline: Option<Instant>) -> io::Result<usize> {
+    let mut written = 0;
+    while written < data.len() {
+        if !ready_before(stream.as_raw_fd(), libc::POLLOUT, deadline)? {
+            break;
+        }
+        match stream.write(&data[written..]) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(bytes_written) => written += bytes_written,
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(written)
+}
+
+// Sends what is left of the previous message, then `buffer`. False when none of `buffer` went
+// out before `deadline`; once part of it did, the rest is kept in `pending` so the peer never
+// sees half a message followed by the next one.
+fn send(stream: &mut TcpStream, pending: &mut Vec<u8>, buffer: &[u8], deadline: Option<Instant>) -> io::Result<bool> {
+    if !pending.is_empty() {
+        let written = write_before(stream, pending, deadline)?;
+        pending.drain(..written);
+        if !pending.is_empty() {
+            return Ok(false);
+        }
+    }
+    let written = write_before(stream, buffer, deadline)?;
+    if written == 0 && !buffer.is_empty() {
+        return Ok(false);
+    }
+    pending.extend_from_slice(&buffer[written..]);
+    Ok(true)
+}
+
+pub struct TcpClientInterface {
+    remote_addr: String,
+    remote_port: u16,
+    stream: Option<TcpStream>,
+    tx_pending: Vec<u8>,
+    base_interface: BaseInterface,
+}
+
+impl TcpClientInterface {
+    pub fn new(name: String, description: String, remote_addr: String, remote_port: u16, log_if: Option<bool>) -> Self {
+        let mut interface = TcpClientInterface {
+            remote_addr,
+            remote_port,
+            stream: None,
+            tx_pending: Vec::new(),
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::Ethernet, logic: LogicalInterface::Socket},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::TcpIp,
+                                            log_if),
+        };
+        // Dropped links come back on their own unless the policy is cleared
+        interface.set_reconnect_policy(Some(ReconnectPolicy::default()));
+        interface
+    }
+
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.as_ref().and_then(|stream| stream.peer_addr().ok())
+    }
+
+    fn connect(&mut self) -> Result<(), InterfaceError> {
+        let socket_addrs: Vec<SocketAddr> = match (self.remote_addr.as_str(), self.remote_port).to_socket_addrs() {
+            Ok(addrs) => addrs.collect(),
+            Err(_) => {
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotValidSocketAddr));
+            }
+        };
+        let stream = TcpStream::connect(&socket_addrs[..]).map_err(|e| self.base_interface.raise_io(e))?;
+        stream.set_nodelay(true).map_err(|e| self.base_interface.raise_io(e))?;
+        // Reads and writes wait on poll, so a slow peer can not hold them past their timeout
+        stream.set_nonblocking(true).map_err(|e| self.base_interface.raise_io(e))?;
+        self.stream = Some(stream);
+        self.tx_pending.clear();
+        self.base_interface.status = InterfaceStatus::Connected;
+        self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+        Ok(())
+    }
+
+    // Called when the peer went away: either re-establish the link or report it lost
+    fn connection_lost(&mut self) -> Result<(), InterfaceError> {
+        self.stream = None;
+        self.tx_pending.clear();
+        recover(self)
+    }
+
+    fn stream_fd(&mut self) -> Result<RawFd, InterfaceError> {
+        if let InterfaceStatus::Reconnecting = self.base_interface.get_status() {
+            recover(self)?;
+        }
+        match self.stream.as_ref() {
+            Some(stream) => Ok(stream.as_raw_fd()),
+            None => Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+        }
+    }
+}
+
+impl InterfaceTrait for TcpClientInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.stream.iter().map(|stream| stream.as_raw_fd()).collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        self.connect()
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        if let Some(stream) = self.stream.take() {
+            // The peer may already be gone, nothing left to report in that case
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        // A dropped connection is recovered following the reconnect policy, then read again
+        loop {
+            let fd = self.stream_fd()?;
+            match ready_before(fd, libc::POLLIN, deadline) {
+                Ok(true) => {}
+                Ok(false) => return Err(self.base_interface.timed_out()),
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+            match self.stream.as_mut().unwrap().read(buffer) {
+                Ok(0) => self.connection_lost()?,
+                Ok(bytes_read) => {
+                    self.base_interface.error = None;
+                    self.base_interface.data_received(bytes_read);
+                    return Ok(bytes_read as u32);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if is_connection_lost(e.kind()) => self.connection_lost()?,
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+    }
+
+    // Fails with Timeout when none of the buffer could be sent within the write timeout, a
+    // part that did not fit goes out ahead of the next write
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        let deadline = self.base_interface.wait_timeout(true).map(|timeout| Instant::now() + timeout);
+        loop {
+            self.stream_fd()?;
+            match send(self.stream.as_mut().unwrap(), &mut self.tx_pending, buffer, deadline) {
+                Ok(true) => {
+                    self.base_interface.error = None;
+                    self.base_interface.data_sent(buffer.len());
+                    return Ok(());
+                }
+                Ok(false) => return Err(self.base_interface.timed_out()),
+                Err(e) if is_connection_lost(e.kind()) => self.connection_lost()?,
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+    }
+}
+
+struct TcpClient {
+    stream: TcpStream,
+    addr: SocketAddr,
+    // Tail of a message the client could not take yet, sent ahead of the next one
+    pending: Vec<u8>,
+}
+
+pub struct TcpServerInterface {
+    ip_address: String,
+    port: u16,
+    max_clients: Option<usize>,
+    listener: Option<TcpListener>,
+    clients: Vec<TcpClient>,
+    next_client: usize,
+    base_interface: BaseInterface,
+}
+
+impl TcpServerInterface {
+    pub fn new(name: String, description: String, ip_address: String, port: u16, log_if: Option<bool>) -> Self {
+        if format!("{}:{}", ip_address, port).parse::<SocketAddr>().is_err() {
+            panic!("Invalid IP address or port");
+        }
+        TcpServerInterface {
+            ip_address,
+            port,
+            max_clients: None,
+            listener: None,
+            clients: Vec::new(),
+            next_client: 0,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::Ethernet, logic: LogicalInterface::Socket},
+                                            InterfaceMode::ReadWrite,
+                                            InterfaceProtocol::TcpIp,
+                                            log_if),
+        }
+    }
+
+    // None accepts any number of clients, Some(1) gives a single-peer server
+    pub fn set_max_clients(&mut self, max_clients: Option<usize>) {
+        self.max_clients = max_clients;
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.as_ref().and_then(|listener| listener.local_addr().ok())
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn client_addrs(&self) -> Vec<SocketAddr> {
+        self.clients.iter().map(|client| client.addr).collect()
+    }
+
+    fn accept_pending(&mut self) -> Result<(), InterfaceError> {
+        let listener = match self.listener.as_ref() {
+            Some(listener) => listener,
+            None => {
+                return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+            }
+        };
+        loop {
+            match listener.accept() {
+                Ok((stream, addr)) => {
+                    if self.max_clients.is_some_and(|max_clients| self.clients.len() >= max_clients) {
+                        let _ = stream.shutdown(Shutdown::Both);
+                        continue;
+                    }
+                    // Accepted streams stay non-blocking like the listener, a slow client must
+                    // not hold up the others
+                    stream.set_nonblocking(true).map_err(|e| self.base_interface.raise_io(e))?;
+                    stream.set_nodelay(true).map_err(|e| self.base_interface.raise_io(e))?;
+                    self.clients.push(TcpClient { stream, addr, pending: Vec::new() });
+                    self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(self.base_interface.raise_io(e)),
+            }
+        }
+    }
+
+    fn drop_client(&mut self, index: usize) {
+        let client = self.clients.remove(index);
+        let _ = client.stream.shutdown(Shutdown::Both);
+        self.base_interface.set_event(InterfaceEvent::ConnectionLost);
+    }
+
+    // Waits until the listener or any client is readable and returns the readable client
+    // indexes, Timeout once `deadline` has passed
+    fn wait_readable(&mut self, deadline: Option<Instant>) -> Result<Vec<usize>, InterfaceError> {
+        let mut poll_fds: Vec<libc::pollfd> = Vec::with_capacity(self.clients.len() + 1);
+        if let Some(listener) = self.listener.as_ref() {
+            poll_fds.push(libc::pollfd { fd: listener.as_raw_fd(), events: libc::POLLIN, revents: 0 });
+        }
+        for client in self.clients.iter() {
+            poll_fds.push(libc::pollfd { fd: client.stream.as_raw_fd(), events: libc::POLLIN, revents: 0 });
+        }
+        let timeout_ms = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now())
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+        let ret = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, timeout_ms) };
+        if ret == 0 {
+            return Err(self.base_interface.timed_out());
+        }
+        if ret < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            return Err(self.base_interface.raise_io(e));
+        }
+        Ok(poll_fds.iter()
+            .skip(1)
+            .enumerate()
+            .filter(|(_, poll_fd)| poll_fd.revents != 0)
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    // Fan-in read returning the address of the client the data came from
+    pub fn read_from(&mut self, buffer: &mut [u8]) -> Result<(u32, SocketAddr), InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        let deadline = self.base_interface.wait_timeout(false).map(|timeout| Instant::now() + timeout);
+        loop {
+            self.accept_pending()?;
+            let mut readable = self.wait_readable(deadline)?;
+            if readable.is_empty() {
+                continue;
+            }
+            // Start after the last served client so a chatty peer cannot starve the others
+            let client_count = self.clients.len();
+            let start = self.next_client % client_count;
+            readable.sort_by_key(|index| (*index + client_count - start) % client_count);
+            for index in readable {
+                let client = &mut self.clients[index];
+                let addr = client.addr;
+                match client.stream.read(buffer) {
+                    Ok(0) if !buffer.is_empty() => {
+                        self.drop_client(index);
+                        break;
+                    }
+                    Ok(bytes_read) => {
+                        self.next_client = index + 1;
+                        self.base_interface.error = None;
+                        self.base_interface.data_received(bytes_read);
+                        return Ok((bytes_read as u32, addr));
+                    }
+                    Err(e) if is_connection_lost(e.kind()) => {
+                        self.drop_client(index);
+                        break;
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(self.base_interface.raise_io(e)),
+                }
+            }
+        }
+    }
+}
+
+impl InterfaceTrait for TcpServerInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    // The listener is included, a pending connection is accepted by the next read
+    fn poll_fds(&self) -> Vec<RawFd> {
+        self.listener.iter()
+            .map(|listener| listener.as_raw_fd())
+            .chain(self.clients.iter().map(|client| client.stream.as_raw_fd()))
+            .collect()
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        let listener = TcpListener::bind((self.ip_address.as_str(), self.port))
+            .map_err(|e| self.base_interface.raise_io(e))?;
+        listener.set_nonblocking(true).map_err(|e| self.base_interface.raise_io(e))?;
+        self.listener = Some(listener);
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        for client in self.clients.drain(..) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+        self.listener = None;
+        self.next_client = 0;
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        let (bytes_read, _) = self.read_from(buffer)?;
+        Ok(bytes_read)
+    }
+
+    // Fan-out write: every connected client receives the buffer, failing clients are dropped
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.accept_pending()?;
+        // The write timeout covers the whole fan-out. A client that can not take any of the
+        // buffer within it is dropped as well, non-blocking writes skip a client whose send
+        // buffer is full for now instead. What did not fit is kept per client.
+        let nonblocking = self.base_interface.is_nonblocking();
+        let deadline = self.base_interface.wait_timeout(true).map(|timeout| Instant::now() + timeout);
+        let mut delivered = 0;
+        let mut skipped = false;
+        let mut index = 0;
+        while index < self.clients.len() {
+            let client = &mut self.clients[index];
+            match send(&mut client.stream, &mut client.pending, buffer, deadline) {
+                Ok(true) => {
+                    delivered += 1;
+                    index += 1;
+                }
+                Ok(false) if nonblocking => {
+                    skipped = true;
+                    index += 1;
+                }
+                Ok(false) | Err(_) => self.drop_client(index),
+            }
+        }
+        // Nothing was sent without any client to send to
+        if delivered == 0 {
+            return match skipped {
+                true => Err(self.base_interface.timed_out()),
+                false => Ok(()),
+            };
+        }
+        self.base_interface.error = None;
+        self.base_interface.data_sent(buffer.len());
+        Ok(())
+    }
+}
+
+// Non-blocking connect, the task is parked until the handshake completed or failed
+async fn connect_async(addr: SocketAddr) -> io::Result<TcpStream> {
+    let domain = if addr.is_ipv4() { libc::AF_INET } else { libc::AF_INET6 };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stream = TcpStream::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    let (storage, length) = raw_socket_addr(&addr);
+    if unsafe { libc::connect(fd, &storage as *const _ as *const libc::sockaddr, length) } < 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(e);
+        }
+        let connected = future::poll_fn(|cx| poll_io(fd, true, cx, || {
+            match poll_ready(fd, libc::POLLOUT, Some(Duration::ZERO)) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(ErrorKind::WouldBlock.into()),
+                Err(e) => Err(e),
+            }
+        })).await;
+        if let Err(e) = connected.and_then(|_| stream.take_error()?.map_or(Ok(()), Err)) {
+            deregister(fd);
+            return Err(e);
+        }
+    }
+    Ok(stream)
+}
+
+// TcpClientInterface driven through the shared reactor. The reconnect policy does not apply:
+// a dropped connection fails with ConnectionLost and the interface has to be opened again.
+pub struct AsyncTcpClientInterface {
+    inner: TcpClientInterface,
+}
+
+impl AsyncTcpClientInterface {
+    pub fn new(inner: TcpClientInterface) -> Self {
+        AsyncTcpClientInterface { inner }
+    }
+
+    pub fn base_interface(&self) -> &BaseInterface {
+        &self.inner.base_interface
+    }
+
+    pub fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.inner.base_interface
+    }
+
+    fn release(&mut self) {
+        if let Some(stream) = self.inner.stream.as_ref() {
+            deregister(stream.as_raw_fd());
+        }
+    }
+
+    fn connection_lost(&mut self) -> InterfaceError {
+        self.release();
+        self.inner.stream = None;
+        self.inner.base_interface.status = InterfaceStatus::Disconnected;
+        self.inner.base_interface.set_event(InterfaceEvent::ConnectionLost);
+        self.inner.base_interface.raise(InterfaceErrorKind::ConnectionLost)
+    }
+}
+
+impl Drop for AsyncTcpClientInterface {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl AsyncInterfaceTrait for AsyncTcpClientInterface {
+    fn get_name(&self) -> String {
+        self.inner.base_interface.get_name()
+    }
+
+    fn get_status(&self) -> InterfaceStatus {
+        self.inner.base_interface.get_status()
+    }
+
+    fn open(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            if let InterfaceStatus::Connected = self.inner.base_interface.get_status() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+            }
+            // Name resolution itself still blocks, use an address to avoid it
+            let socket_addrs: Vec<SocketAddr> = match (self.inner.remote_addr.as_str(), self.inner.remote_port).to_socket_addrs() {
+                Ok(addrs) => addrs.collect(),
+                Err(_) => {
+                    return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotValidSocketAddr));
+                }
+            };
+            let mut last_error = None;
+            for addr in socket_addrs {
+                match connect_async(addr).await {
+                    Ok(stream) => {
+                        stream.set_nodelay(true).map_err(|e| self.inner.base_interface.raise_io(e))?;
+                        self.inner.stream = Some(stream);
+                        self.inner.base_interface.status = InterfaceStatus::Connected;
+                        self.inner.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+                        return Ok(());
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(match last_error {
+                Some(e) => self.inner.base_interface.raise_io(e),
+                None => self.inner.base_interface.raise(InterfaceErrorKind::NotValidSocketAddr),
+            })
+        })
+    }
+
+    fn close(&mut self) -> InterfaceFuture<'_, ()> {
+        Box::pin(async move {
+            self.release();
+            self.inner.close()
+        })
+    }
+
+    fn read<'a>(&'a mut self, buffer: &'a mut [u8]) -> InterfaceFuture<'a, u32> {
+        Box::pin(async move {
+            if let InterfaceMode::Write = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+            }
+            if buffer.is_empty() {
+                return Ok(0);
+            }
+            let stream = match self.inner.stream.as_mut() {
+                Some(stream) => stream,
+                None => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            let fd = stream.as_raw_fd();
+            match future::poll_fn(|cx| poll_io(fd, false, cx, || stream.read(buffer))).await {
+                Ok(0) => Err(self.connection_lost()),
+                Ok(bytes_read) => {
+                    self.inner.base_interface.error = None;
+                    self.inner.base_interface.data_received(bytes_read);
+                    Ok(bytes_read as u32)
+                }
+                Err(e) if is_connection_lost(e.kind()) => Err(self.connection_lost()),
+                Err(e) => Err(self.inner.base_interface.raise_io(e)),
+            }
+        })
+    }
+
+    fn write<'a>(&'a mut self, buffer: &'a [u8]) -> InterfaceFuture<'a, ()> {
+        Box::pin(async move {
+            if let InterfaceMode::Read = self.inner.base_interface.get_mode() {
+                return Err(self.inner.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+            }
+            let stream = match self.inner.stream.as_mut() {
+                Some(stream) => stream,
+                None => return Err(self.inner.base_interface.raise(InterfaceErrorKind::NotOpenIFace)),
+            };
+            let fd = stream.as_raw_fd();
+            let mut written = 0;
+            while written < buffer.len() {
+                match future::poll_fn(|cx| poll_io(fd, true, cx, || stream.write(&buffer[written..]))).await {
+                    Ok(0) => return Err(self.connection_lost()),
+                    Ok(bytes_written) => written += bytes_written,
+                    Err(e) if is_connection_lost(e.kind()) => return Err(self.connection_lost()),
+                    Err(e) => return Err(self.inner.base_interface.raise_io(e)),
+                }
+            }
+            self.inner.base_interface.error = None;
+            self.inner.base_interface.data_sent(buffer.len());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+
+use super::reconnect::recover;
+use super::{BaseInterface, InterfaceError, InterfaceErrorKind, InterfaceEvent, InterfaceMode,
+            InterfaceProtocol, InterfaceStatus, InterfaceTrait, InterfaceType, LogicalInterface,
+            PhysInterface};
+
+#[derive(Clone)]
+pub enum UnixSocketKind {
+    Stream,
+    Datagram,
+}
+
+pub struct UnixSocketInterface {
+    socket_path: String,
+    remote_path: Option<String>,
+    kind: UnixSocketKind,
+    listen: bool,
+    listener: Option<UnixListener>,
+    stream: Option<UnixStream>,
+    datagram: Option<UnixDatagram>,
+    bound: bool,
+    base_interface: BaseInterface,
+}
+
+impl UnixSocketInterface {
+    pub fn new(name: String, description: String, socket_path: String, kind: UnixSocketKind, mode: InterfaceMode, log_if: Option<bool>) -> Self {
+        UnixSocketInterface {
+            socket_path,
+            remote_path: None,
+            kind,
+            listen: false,
+            listener: None,
+            stream: None,
+            datagram: None,
+            bound: false,
+            base_interface: BaseInterface::new(name,
+                                            description,
+                                            InterfaceType{phys: PhysInterface::None, logic: LogicalInterface::Socket},
+                                            mode,
+                                            InterfaceProtocol::Raw,
+                                            log_if),
+        }
+    }
+
+    // Stream sockets only: bind socket_path and accept a peer instead of connecting to it
+    pub fn set_listen(&mut self, listen: bool) {
+        self.listen = listen;
+    }
+
+    // Datagram sockets only: path of the peer socket that receives our writes
+    pub fn append_remote_path(&mut self, remote_path: String) {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            self.base_interface.set_error(InterfaceErrorKind::ReadOnWriteOnly);
+            return;
+        }
+        self.remote_path = Some(remote_path);
+    }
+
+    // A socket file left behind by a previous run would make bind fail, anything else at
+    // socket_path is left alone
+    fn remove_stale_socket(&mut self) -> Result<(), InterfaceError> {
+        let metadata = match fs::symlink_metadata(&self.socket_path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(self.base_interface.raise_io(e)),
+        };
+        if !metadata.file_type().is_socket() {
+            let e = io::Error::new(ErrorKind::AddrInUse, format!("{} exists and is not a socket", self.socket_path));
+            return Err(self.base_interface.raise_io(e));
+        }
+        fs::remove_file(&self.socket_path).map_err(|e| self.base_interface.raise_io(e))
+    }
+
+    // Listening stream sockets wait here for their peer on first use
+    fn accept_peer(&mut self) -> Result<(), InterfaceError> {
+        if self.stream.is_some() {
+            return Ok(());
+        }
+        if let Some(listener) = self.listener.as_ref() {
+            self.base_interface.wait_ready(listener.as_raw_fd(), false)?;
+            let (stream, _) = listener.accept().map_err(|e| self.base_interface.raise_io(e))?;
+            self.stream = Some(stream);
+            self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+            Ok(())
+        } else {
+            Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+        }
+    }
+
+    // Listening sockets wait for the next peer, connecting ones follow the reconnect policy
+    fn peer_lost(&mut self) -> Result<(), InterfaceError> {
+        self.stream = None;
+        if !self.listen {
+            return recover(self);
+        }
+        self.base_interface.set_event(InterfaceEvent::ConnectionLost);
+        Err(self.base_interface.raise(InterfaceErrorKind::ConnectionLost))
+    }
+}
+
+impl InterfaceTrait for UnixSocketInterface {
+    fn base_interface(&self) -> &BaseInterface {
+        &self.base_interface
+    }
+
+    fn base_interface_mut(&mut self) -> &mut BaseInterface {
+        &mut self.base_interface
+    }
+
+    // A listening socket without a peer yet is readable once the peer connects
+    fn poll_fds(&self) -> Vec<RawFd> {
+        match (self.stream.as_ref(), self.listener.as_ref(), self.datagram.as_ref()) {
+            (Some(stream), _, _) => vec![stream.as_raw_fd()],
+            (None, Some(listener), _) => vec![listener.as_raw_fd()],
+            (None, None, Some(datagram)) => vec![datagram.as_raw_fd()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn open(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Connected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::AlreadyOpenIFace));
+        }
+        match self.kind {
+            UnixSocketKind::Stream => {
+                if self.listen {
+                    self.remove_stale_socket()?;
+                    self.listener = Some(UnixListener::bind(&self.socket_path)
+                        .map_err(|e| self.base_interface.raise_io(e))?);
+                    self.bound = true;
+                } else {
+                    self.stream = Some(UnixStream::connect(&self.socket_path)
+                        .map_err(|e| self.base_interface.raise_io(e))?);
+                    self.base_interface.set_event(InterfaceEvent::ConnectionEstablished);
+                }
+            }
+            UnixSocketKind::Datagram => {
+                // A write-only datagram socket has nothing to receive, so it stays unbound
+                if let InterfaceMode::Write = self.base_interface.get_mode() {
+                    self.datagram = Some(UnixDatagram::unbound()
+                        .map_err(|e| self.base_interface.raise_io(e))?);
+                } else {
+                    self.remove_stale_socket()?;
+                    self.datagram = Some(UnixDatagram::bind(&self.socket_path)
+                        .map_err(|e| self.base_interface.raise_io(e))?);
+                    self.bound = true;
+                }
+            }
+        }
+        self.base_interface.status = InterfaceStatus::Connected;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), InterfaceError> {
+        if let InterfaceStatus::Disconnected = self.base_interface.get_status() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace));
+        }
+        self.stream = None;
+        self.listener = None;
+        self.datagram = None;
+        if self.bound {
+            self.bound = false;
+            fs::remove_file(&self.socket_path).map_err(|e| self.base_interface.raise_io(e))?;
+        }
+        self.base_interface.status = InterfaceStatus::Disconnected;
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<u32, InterfaceError> {
+        if let InterfaceMode::Write = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::WriteOnReadOnly));
+        }
+        match self.base_interface.get_status() {
+            InterfaceStatus::Connected => {
+                self.base_interface.error = None;
+                let bytes_read = match self.kind {
+                    UnixSocketKind::Stream => loop {
+                        self.accept_peer()?;
+                        let stream = self.stream.as_mut().unwrap();
+                        self.base_interface.wait_ready(stream.as_raw_fd(), false)?;
+                        match stream.read(buffer) {
+                            Ok(0) if !buffer.is_empty() => self.peer_lost()?,
+                            Ok(bytes_read) => break bytes_read,
+                            Err(e) if e.kind() == ErrorKind::ConnectionReset => self.peer_lost()?,
+                            Err(e) => return Err(self.base_interface.raise_io(e)),
+                        }
+                    },
+                    UnixSocketKind::Datagram => {
+                        if let Some(datagram) = self.datagram.as_ref() {
+                            self.base_interface.wait_ready(datagram.as_raw_fd(), false)?;
+                            datagram.recv(buffer).map_err(|e| self.base_interface.raise_io(e))?
+                        } else {
+                            return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
+                        }
+                    }
+                };
+                self.base_interface.data_received(bytes_read);
+                Ok(bytes_read as u32)
+            }
+            InterfaceStatus::Reconnecting => {
+                recover(self)?;
+                self.read(buffer)
+            }
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> Result<(), InterfaceError> {
+        if let InterfaceMode::Read = self.base_interface.get_mode() {
+            return Err(self.base_interface.raise(InterfaceErrorKind::ReadOnWriteOnly));
+        }
+        match self.base_interface.get_status() {
+            InterfaceStatus::Connected => {
+                self.base_interface.error = None;
+                match self.kind {
+                    UnixSocketKind::Stream => loop {
+                        self.accept_peer()?;
+                        let stream = self.stream.as_mut().unwrap();
+                        self.base_interface.wait_ready(stream.as_raw_fd(), true)?;
+                        match stream.write_all(buffer) {
+                            Ok(()) => break,
+                            Err(e) if matches!(e.kind(), ErrorKind::BrokenPipe | ErrorKind::ConnectionReset) => {
+                                self.peer_lost()?;
+                            }
+                            Err(e) => return Err(self.base_interface.raise_io(e)),
+                        }
+                    },
+                    UnixSocketKind::Datagram => {
+                        match (self.datagram.as_ref(), self.remote_path.as_ref()) {
+                            (Some(datagram), Some(remote_path)) => {
+                                self.base_interface.wait_ready(datagram.as_raw_fd(), true)?;
+                                datagram.send_to(buffer, remote_path)
+                                    .map_err(|e| self.base_interface.raise_io(e))?;
+                            }
+                            _ => {
+                                return Err(self.base_interface.raise(InterfaceErrorKind::GenericError));
+                            }
+                        }
+                    }
+                }
+                self.base_interface.data_sent(buffer.len());
+                Ok(())
+            }
+            InterfaceStatus::Reconnecting => {
+                recover(self)?;
+                self.write(buffer)
+            }
+            _ => {
+                Err(self.base_interface.raise(InterfaceErrorKind::NotOpenIFace))
+            }
+        }
+    }
+}
@@ -38,6 +38,12 @@ impl DataProcessor {
     pub fn timestamp(&self) -> f64 {
         self.timestamp_sec as f64 + self.timestamp_nsec as f64 * 1e-9
     }
+    pub fn timestamp_sec(&self) -> u64 {
+        self.timestamp_sec
+    }
+    pub fn timestamp_nsec(&self) -> u64 {
+        self.timestamp_nsec
+    }
     pub fn data_size(&self) -> u64 {
         self.data_size
     }